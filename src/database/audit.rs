@@ -0,0 +1,49 @@
+use deadpool_postgres::Transaction;
+use serde_json::Value;
+use tokio_postgres::types::Json;
+
+use crate::utils::thread_state::generate_id;
+
+/// Moderation action for mod_audit
+/// user_id is moderator, towards_to is affected user
+#[derive(Debug)]
+pub struct AuditEntry<'a> {
+    pub user_id: &'a str,
+    pub towards_to: &'a str,
+    pub role_id: i32,
+    pub target_type: &'a str,
+    pub target_id: &'a str,
+    pub action_type: &'a str,
+    pub reason: &'a str,
+    pub metadata: Option<Value>,
+    pub old_content: Option<Value>,
+}
+
+/// Writes moderation action into mod_audit, returns its id
+pub async fn create_audit_entry(entry: AuditEntry<'_>, tx: &mut Transaction<'_>) -> String {
+    let id = generate_id().to_string();
+    tx.execute(
+        "
+        INSERT INTO mod_audit (
+            id, user_id, towards_to, role_id, target_type,
+            target_id, action_type, reason, metadata, old_content
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        ",
+        &[
+            &id,
+            &entry.user_id,
+            &entry.towards_to,
+            &entry.role_id.to_string(),
+            &entry.target_type,
+            &entry.target_id,
+            &entry.action_type,
+            &entry.reason,
+            &entry.metadata.map(Json),
+            &entry.old_content.map(Json),
+        ],
+    )
+    .await
+    .unwrap();
+    id
+}
@@ -166,6 +166,7 @@ pub async fn email_exists(email: &String, conn: &mut LazyConn) -> bool {
 }
 
 /// Check username
+/// Old usernames that are still reserved after a change count as existing
 pub async fn username_exists(username: &String, conn: &mut LazyConn) -> bool {
     let db = conn.get_client().await.unwrap();
 
@@ -174,6 +175,9 @@ pub async fn username_exists(username: &String, conn: &mut LazyConn) -> bool {
             "
             SELECT 1 FROM users
             WHERE username = $1
+            UNION ALL
+            SELECT 1 FROM username_history
+            WHERE username = $1 AND reserved_until > NOW()
             LIMIT 1
             ",
             &[username],
@@ -0,0 +1,185 @@
+use deadpool_postgres::Transaction;
+use tokio_postgres::Row;
+
+use crate::{database::conn::LazyConn, entities::badge::Badge, utils::storage::normalize_url};
+
+const BADGE_SQL: &str = "
+    SELECT b.badge_id, b.name, b.description,
+           f.objects[1] AS icon_url,
+           EXTRACT(EPOCH FROM b.created_at)::BIGINT AS created_at
+    FROM badges b
+    LEFT JOIN files f ON f.context_id = b.icon_context_id
+";
+
+/// Private function for converting Row to Badge
+fn row_to_badge(row: Row) -> Badge {
+    Badge {
+        badge_id: row.get("badge_id"),
+        name: row.get("name"),
+        description: row.get("description"),
+        icon_url: normalize_url(row.get("icon_url")),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Get whole badge catalog
+pub async fn get_badges(conn: &mut LazyConn) -> Vec<Badge> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(&format!("{} ORDER BY b.badge_id", BADGE_SQL), &[])
+        .await
+        .unwrap();
+    rows.into_iter().map(row_to_badge).collect()
+}
+
+/// Get badge by id
+pub async fn get_badge(badge_id: &i16, conn: &mut LazyConn) -> Option<Badge> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(&format!("{} WHERE b.badge_id = $1", BADGE_SQL), &[badge_id])
+        .await
+        .unwrap();
+    row.map(row_to_badge)
+}
+
+/// Get badges by ids, keeps order of ids and skips unknown ones
+pub async fn get_badges_by_ids(badge_ids: &[i16], conn: &mut LazyConn) -> Vec<Badge> {
+    if badge_ids.is_empty() {
+        return Vec::new();
+    }
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            &format!(
+                "{} WHERE b.badge_id = ANY($1) ORDER BY array_position($1, b.badge_id)",
+                BADGE_SQL
+            ),
+            &[&badge_ids],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(row_to_badge).collect()
+}
+
+/// Creates badge with the next free id
+/// Badges table is locked, so concurrent creations don't pick the same id
+/// Returns None if name is already taken
+pub async fn create_badge(
+    name: &String,
+    description: &Option<String>,
+    icon_context_id: &Option<String>,
+    tx: &mut Transaction<'_>,
+) -> Option<i16> {
+    tx.execute("LOCK TABLE badges IN SHARE ROW EXCLUSIVE MODE", &[])
+        .await
+        .unwrap();
+    let row = tx
+        .query_opt(
+            "
+            INSERT INTO badges (badge_id, name, description, icon_context_id)
+            SELECT COALESCE(MAX(badge_id), 0) + 1, $1, $2, $3
+            FROM badges
+            ON CONFLICT (name) DO NOTHING
+            RETURNING badge_id
+            ",
+            &[name, description, icon_context_id],
+        )
+        .await
+        .unwrap();
+    Some(row?.get("badge_id"))
+}
+
+/// Get badge ids of user, None if user has no profile
+pub async fn get_user_badges(user_id: &String, tx: &mut Transaction<'_>) -> Option<Vec<i16>> {
+    let row = tx
+        .query_opt(
+            "
+            SELECT COALESCE(badges, '{}') AS badges FROM user_profiles
+            WHERE user_id = $1
+            FOR UPDATE
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    row.map(|r| r.get("badges"))
+}
+
+/// Gives badge to user, returns false if user already has it
+pub async fn grant_badge(user_id: &String, badge_id: &i16, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            INSERT INTO user_profiles (user_id, badges)
+            VALUES ($1, ARRAY[$2::SMALLINT])
+            ON CONFLICT (user_id) DO UPDATE
+            SET badges = array_append(COALESCE(user_profiles.badges, '{}'), $2)
+            WHERE NOT ($2 = ANY(COALESCE(user_profiles.badges, '{}')))
+            ",
+            &[user_id, badge_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Takes badge from user, returns false if user didn't have it
+pub async fn revoke_badge(user_id: &String, badge_id: &i16, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            UPDATE user_profiles
+            SET badges = array_remove(badges, $2)
+            WHERE user_id = $1 AND $2 = ANY(badges)
+            ",
+            &[user_id, badge_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx, get_conn,
+        utils::{state::AppState, thread_state::generate_id},
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn concurrent_badges_get_distinct_ids() {
+        let state = Arc::new(AppState::for_tests());
+        let names = [
+            format!("badge_{}", generate_id()),
+            format!("badge_{}", generate_id()),
+        ];
+
+        let create = |name: String| {
+            let state = state.clone();
+            async move {
+                let mut conn = get_conn!(state);
+                let mut tx = create_tx!(conn);
+                let badge_id = create_badge(&name, &None, &None, &mut tx).await;
+                tx.commit().await.unwrap();
+                badge_id
+            }
+        };
+        let (first, second) = tokio::join!(create(names[0].clone()), create(names[1].clone()));
+        assert_ne!(first.unwrap(), second.unwrap());
+        // Taken name is reported instead of failing
+        assert_eq!(create(names[0].clone()).await, None);
+
+        let mut conn = get_conn!(state);
+        let db = conn.get_client().await.unwrap();
+        db.execute(
+            "DELETE FROM badges WHERE name = ANY($1)",
+            &[&names.to_vec()],
+        )
+        .await
+        .unwrap();
+    }
+}
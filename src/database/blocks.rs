@@ -0,0 +1,101 @@
+use deadpool_postgres::Transaction;
+
+use crate::database::conn::LazyConn;
+
+/// Check if any of two users blocked the other one
+pub async fn is_blocked_between(user_id: &String, other_id: &String, conn: &mut LazyConn) -> bool {
+    let db = conn.get_client().await.unwrap();
+    let value = db
+        .query_opt(
+            "
+            SELECT 1 FROM blocked_users
+            WHERE (user_id = $1 AND blocked_id = $2)
+               OR (user_id = $2 AND blocked_id = $1)
+            LIMIT 1
+            ",
+            &[user_id, other_id],
+        )
+        .await
+        .unwrap();
+    value.is_some()
+}
+
+/// Blocks user, follows and follow requests between them are removed
+/// Returns false if user was already blocked
+pub async fn block_user(user_id: &String, target_id: &String, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            INSERT INTO blocked_users (user_id, blocked_id)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, blocked_id) DO NOTHING
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+
+    for table in ["followed", "follow_requests"] {
+        tx.execute(
+            &format!(
+                "
+                DELETE FROM {}
+                WHERE (user_id = $1 AND followed_to = $2)
+                   OR (user_id = $2 AND followed_to = $1)
+                ",
+                table
+            ),
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    }
+    affected > 0
+}
+
+/// Unblocks user, returns false if user wasn't blocked
+pub async fn unblock_user(user_id: &String, target_id: &String, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            DELETE FROM blocked_users
+            WHERE user_id = $1 AND blocked_id = $2
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Mutes user, their posts are hidden from feeds but follow stays
+/// Returns false if user was already muted
+pub async fn mute_user(user_id: &String, target_id: &String, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            INSERT INTO muted_users (user_id, muted_id)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, muted_id) DO NOTHING
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Unmutes user, returns false if user wasn't muted
+pub async fn unmute_user(user_id: &String, target_id: &String, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            DELETE FROM muted_users
+            WHERE user_id = $1 AND muted_id = $2
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
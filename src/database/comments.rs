@@ -0,0 +1,14 @@
+use crate::database::conn::LazyConn;
+
+/// Get id of post that comment belongs to
+pub async fn get_comment_post_id(comment_id: &String, conn: &mut LazyConn) -> Option<String> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "SELECT post_id FROM comments WHERE comment_id = $1",
+            &[comment_id],
+        )
+        .await
+        .unwrap();
+    row.map(|r| r.get("post_id"))
+}
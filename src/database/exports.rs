@@ -0,0 +1,390 @@
+use deadpool_postgres::Transaction;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use tokio_postgres::Row;
+
+use crate::{database::conn::LazyConn, utils::thread_state::generate_id};
+
+#[derive(Debug, Serialize)]
+pub struct DataExport {
+    pub export_id: String,
+    pub status: String,
+    pub format: String,
+    pub created_at: i64,
+    pub completed_at: Option<i64>,
+    pub expires_at: Option<i64>,
+}
+
+/// Private function for converting Row to DataExport
+fn row_to_export(row: Row) -> DataExport {
+    DataExport {
+        export_id: row.get("export_id"),
+        status: row.get("status"),
+        format: row.get("format"),
+        created_at: row.get("created_at"),
+        completed_at: row.get("completed_at"),
+        expires_at: row.get("expires_at"),
+    }
+}
+
+/// Get timestamp of the last export requested by user, failed exports aren't counted
+pub async fn get_last_export_time(user_id: &String, conn: &mut LazyConn) -> Option<i64> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_one(
+            "
+            SELECT EXTRACT(EPOCH FROM MAX(created_at))::BIGINT AS created_at
+            FROM data_exports
+            WHERE user_id = $1 AND status IN ('pending', 'ready', 'expired')
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    row.get("created_at")
+}
+
+/// Creates pending export, archive is added later by 'complete_export'
+pub async fn create_export(user_id: &String, format: &str, tx: &mut Transaction<'_>) -> String {
+    let export_id = generate_id().to_string();
+    tx.execute(
+        "
+        INSERT INTO data_exports (export_id, user_id, format)
+        VALUES ($1, $2, $3)
+        ",
+        &[&export_id, user_id, &format],
+    )
+    .await
+    .unwrap();
+    export_id
+}
+
+/// Get exports of user, newest first
+pub async fn get_exports(user_id: &String, conn: &mut LazyConn) -> Vec<DataExport> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            SELECT export_id, status, format,
+                   EXTRACT(EPOCH FROM created_at)::BIGINT AS created_at,
+                   EXTRACT(EPOCH FROM completed_at)::BIGINT AS completed_at,
+                   EXTRACT(EPOCH FROM expires_at)::BIGINT AS expires_at
+            FROM data_exports
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT 10
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(row_to_export).collect()
+}
+
+/// Get archive of ready export
+/// Returns: (format, archive)
+pub async fn get_export_archive(
+    export_id: &String,
+    conn: &mut LazyConn,
+) -> Option<(String, Vec<u8>)> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "
+            SELECT format, archive
+            FROM data_exports
+            WHERE export_id = $1 AND status = 'ready' AND expires_at > NOW()
+            ",
+            &[export_id],
+        )
+        .await
+        .unwrap();
+    row.map(|r| (r.get("format"), r.get("archive")))
+}
+
+/// Stores archive and marks export as ready
+pub async fn complete_export(
+    export_id: &String,
+    archive: &[u8],
+    expires_days: i64,
+    tx: &mut Transaction<'_>,
+) {
+    tx.execute(
+        "
+        UPDATE data_exports
+        SET status = 'ready', archive = $2, completed_at = NOW(),
+            expires_at = NOW() + make_interval(days => $3::INT)
+        WHERE export_id = $1
+        ",
+        &[export_id, &archive, &(expires_days as i32)],
+    )
+    .await
+    .unwrap();
+}
+
+/// Marks export as failed
+pub async fn fail_export(export_id: &String, conn: &mut LazyConn) {
+    let db = conn.get_client().await.unwrap();
+    db.execute(
+        "
+        UPDATE data_exports
+        SET status = 'failed', completed_at = NOW()
+        WHERE export_id = $1
+        ",
+        &[export_id],
+    )
+    .await
+    .unwrap();
+}
+
+/// Marks exports pending for longer than 'timeout_minutes' as failed,
+/// their jobs were lost on restart, so users can request new ones
+pub async fn fail_stale_exports(timeout_minutes: i64, conn: &mut LazyConn) -> u64 {
+    let db = conn.get_client().await.unwrap();
+    db.execute(
+        "
+        UPDATE data_exports
+        SET status = 'failed', completed_at = NOW()
+        WHERE status = 'pending'
+          AND created_at < NOW() - make_interval(mins => $1::INT)
+        ",
+        &[&(timeout_minutes as i32)],
+    )
+    .await
+    .unwrap()
+}
+
+/// Drops archives of expired exports, rows are kept for rate limiting
+pub async fn expire_exports(conn: &mut LazyConn) -> u64 {
+    let db = conn.get_client().await.unwrap();
+    db.execute(
+        "
+        UPDATE data_exports
+        SET status = 'expired', archive = NULL
+        WHERE status = 'ready' AND expires_at < NOW()
+        ",
+        &[],
+    )
+    .await
+    .unwrap()
+}
+
+/// Sections of personal data export, every query gets user_id as $1
+const USER_DATA_SQL: &[(&str, &str)] = &[
+    (
+        "profile",
+        "
+        SELECT u.user_id, u.username, u.email, u.email_verified, u.role_id,
+               u.followers_count, u.following_count, u.username_changed_at,
+               p.display_name, p.bio, p.languages, p.badges, p.is_private,
+               ac.objects AS avatar, bc.objects AS banner
+        FROM users u
+        LEFT JOIN user_profiles p ON p.user_id = u.user_id
+        LEFT JOIN files ac ON ac.context_id = p.avatar_context_id
+        LEFT JOIN files bc ON bc.context_id = p.banner_context_id
+        WHERE u.user_id = $1
+        ",
+    ),
+    (
+        "settings",
+        "SELECT settings, updated_at FROM user_settings WHERE user_id = $1",
+    ),
+    (
+        "username_history",
+        "
+        SELECT username, changed_at FROM username_history
+        WHERE user_id = $1 ORDER BY changed_at
+        ",
+    ),
+    (
+        "posts",
+        "
+        SELECT p.post_id, p.content, p.created_at, p.updated_at, p.flags,
+               p.status, p.visibility, p.is_deleted, p.deleted_at, p.likes_count,
+               p.dislikes_count, p.comments_count, f.objects AS media,
+               ARRAY(
+                   SELECT t.name FROM post_tags pt
+                   JOIN tags t ON t.tag_id = pt.tag_id
+                   WHERE pt.post_id = p.post_id
+               ) AS tags
+        FROM posts p
+        LEFT JOIN files f ON f.context_id = p.file_context_id
+        WHERE p.user_id = $1
+        ORDER BY p.created_at
+        ",
+    ),
+    (
+        "post_revisions",
+        "
+        SELECT r.revision_id, r.post_id, r.content, r.tags, r.visibility, r.created_at,
+               f.objects AS media
+        FROM post_revisions r
+        JOIN posts p ON p.post_id = r.post_id
+        LEFT JOIN files f ON f.context_id = r.file_context_id
+        WHERE p.user_id = $1
+        ORDER BY r.created_at
+        ",
+    ),
+    (
+        "comments",
+        "
+        SELECT comment_id, post_id, parent_comment_id, content, type,
+               likes_count, dislikes_count, replies_count
+        FROM comments WHERE user_id = $1
+        ",
+    ),
+    (
+        "reactions",
+        "
+        SELECT post_id, comment_id, is_like, created_at
+        FROM reactions WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "favorites",
+        "
+        SELECT post_id, comment_id, created_at
+        FROM favorites WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "poll_votes",
+        "
+        SELECT post_id, options, created_at
+        FROM poll_voters WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "post_views",
+        "
+        SELECT post_id, timestamp
+        FROM user_post_views WHERE user_id = $1 ORDER BY timestamp
+        ",
+    ),
+    (
+        "following",
+        "
+        SELECT followed_to AS user_id, created_at
+        FROM followed WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "followers",
+        "
+        SELECT user_id, created_at
+        FROM followed WHERE followed_to = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "blocked_users",
+        "
+        SELECT blocked_id AS user_id, created_at
+        FROM blocked_users WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "muted_users",
+        "
+        SELECT muted_id AS user_id, created_at
+        FROM muted_users WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "friends",
+        "
+        SELECT friend_id AS user_id, created_at
+        FROM friends WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "messages",
+        "
+        SELECT m.message_id, m.channel_id, m.content, m.content_type,
+               m.created_at, m.edited_at, f.objects AS media
+        FROM messages m
+        LEFT JOIN files f ON f.context_id = m.file_context_id
+        WHERE m.user_id = $1
+        ORDER BY m.created_at
+        ",
+    ),
+    (
+        "notifications",
+        "
+        SELECT id, type, message, from_id, linked_type, linked_id,
+               second_linked_id, unread
+        FROM user_notifications WHERE user_id = $1
+        ",
+    ),
+    (
+        "sessions",
+        "
+        SELECT session_id, created_at
+        FROM auth_keys WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+    (
+        "files",
+        "
+        SELECT context_id, type, objects, created_at
+        FROM files WHERE user_id = $1 ORDER BY created_at
+        ",
+    ),
+];
+
+/// Collects all personal data of user, every section is a JSON array of rows
+pub async fn get_user_data(user_id: &String, conn: &mut LazyConn) -> Map<String, Value> {
+    let db = conn.get_client().await.unwrap();
+    let mut data = Map::new();
+
+    for (section, sql) in USER_DATA_SQL {
+        let sql = format!(
+            "SELECT COALESCE(json_agg(t), '[]'::json) AS data FROM ({}) t",
+            sql
+        );
+        let row = db.query_one(&sql, &[user_id]).await.unwrap();
+        data.insert(section.to_string(), row.get("data"));
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::test_support::{cleanup, new_user},
+        get_conn,
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn failed_exports_are_not_rate_limited() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let user_id = new_user(&mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        let export_id = create_export(&user_id, "json", &mut tx).await;
+        tx.commit().await.unwrap();
+        assert!(get_last_export_time(&user_id, &mut conn).await.is_some());
+
+        // Fresh job is still running
+        fail_stale_exports(60, &mut conn).await;
+        assert!(get_last_export_time(&user_id, &mut conn).await.is_some());
+
+        let db = conn.get_client().await.unwrap();
+        db.execute(
+            "UPDATE data_exports SET created_at = NOW() - INTERVAL '2 hours' WHERE export_id = $1",
+            &[&export_id],
+        )
+        .await
+        .unwrap();
+        assert!(fail_stale_exports(60, &mut conn).await >= 1);
+        assert_eq!(get_last_export_time(&user_id, &mut conn).await, None);
+
+        cleanup(&[&user_id], &mut conn).await;
+    }
+}
@@ -0,0 +1,112 @@
+use deadpool_postgres::Transaction;
+
+/// Saves post, or comment if comment_id is given, to user's favorites
+/// Returns false if it was already saved
+pub async fn add_favorite(
+    user_id: &String,
+    post_id: &String,
+    comment_id: Option<&String>,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    // Conflict target has to match one of the partial unique indexes
+    let conflict = match comment_id {
+        None => "(post_id, user_id) WHERE comment_id IS NULL",
+        Some(_) => "(comment_id, user_id) WHERE comment_id IS NOT NULL",
+    };
+    let affected = tx
+        .execute(
+            &format!(
+                "
+                INSERT INTO favorites (user_id, post_id, comment_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT {} DO NOTHING
+                ",
+                conflict
+            ),
+            &[user_id, post_id, &comment_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Removes post or comment from user's favorites, returns false if it wasn't saved
+pub async fn remove_favorite(
+    user_id: &String,
+    post_id: &String,
+    comment_id: Option<&String>,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let affected = tx
+        .execute(
+            "
+            DELETE FROM favorites
+            WHERE user_id = $1 AND post_id = $2
+              AND comment_id IS NOT DISTINCT FROM $3
+            ",
+            &[user_id, post_id, &comment_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::{
+            conn::LazyConn,
+            posts::{get_favorite_posts, get_post},
+            test_support::{cleanup, ids, new_post, new_user},
+        },
+        entities::post::Post,
+        get_conn,
+        utils::{pagination::TimeCursorParams, state::AppState},
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn favorites_are_listed_in_saved_order() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let viewer = new_user(&mut conn).await;
+        let mut created = Vec::new();
+        for _ in 0..3 {
+            created.push(new_post(&author, &[], &mut conn).await);
+        }
+
+        // Saved in reverse order of creation, one post twice
+        for post_id in [&created[1], &created[0], &created[2], &created[0]] {
+            let mut tx = create_tx!(conn);
+            add_favorite(&viewer, post_id, None, &mut tx).await;
+            tx.commit().await.unwrap();
+        }
+        let saved: Vec<&str> = vec![&created[2], &created[0], &created[1]];
+
+        let mut cursor = TimeCursorParams {
+            before: None,
+            limit: Some(2),
+        };
+        let first = get_favorite_posts(&viewer, &cursor, &mut conn, &state).await;
+        let first_ids: Vec<&str> = first.iter().map(|(_, p)| p.post_id.as_str()).collect();
+        assert_eq!(first_ids, saved[0..2]);
+        assert!(first.iter().all(|(_, p)| p.is_favorited));
+
+        cursor.before = first.last().map(|(c, _)| c.to_string().parse().unwrap());
+        let second = get_favorite_posts(&viewer, &cursor, &mut conn, &state).await;
+        let posts: Vec<Post> = second.into_iter().map(|(_, p)| p).collect();
+        assert_eq!(ids(&posts), saved[2..3]);
+
+        let post = get_post(&created[0], &author, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert!(!post.is_favorited);
+
+        cleanup(&[&author, &viewer], &mut conn).await;
+    }
+}
@@ -0,0 +1,66 @@
+use deadpool_postgres::Transaction;
+use tokio_postgres::Row;
+
+use crate::{
+    database::conn::LazyConn,
+    entities::file::{FileContext, FileType},
+    utils::thread_state::generate_id,
+};
+
+/// Private function for converting Row to FileContext
+fn row_to_file_context(row: Row) -> FileContext {
+    FileContext {
+        context_id: row.get("context_id"),
+        user_id: row.get("user_id"),
+        objects: row.get("objects"),
+        r#type: row.get("type"),
+        reference_count: row.get("reference_count"),
+        allowed_count: row.get("allowed_count"),
+    }
+}
+
+/// Get file context by id
+pub async fn get_file_context(context_id: &String, conn: &mut LazyConn) -> Option<FileContext> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "
+            SELECT context_id, user_id, objects, type,
+                   reference_count, allowed_count
+            FROM files
+            WHERE context_id = $1
+            ",
+            &[context_id],
+        )
+        .await
+        .unwrap();
+    row.map(row_to_file_context)
+}
+
+/// Creates file context with 'count' object paths owned by user_id
+/// Objects are not uploaded yet, client uploads them using signed token
+pub async fn create_file_context(
+    user_id: &String,
+    r#type: FileType,
+    count: usize,
+    tx: &mut Transaction<'_>,
+) -> FileContext {
+    let context_id = generate_id().to_string();
+    let objects: Vec<String> = (0..count)
+        .map(|i| format!("{}/{}/{}/{}", r#type.prefix(), user_id, context_id, i))
+        .collect();
+
+    let row = tx
+        .query_one(
+            "
+            INSERT INTO files (context_id, user_id, objects, type)
+            VALUES ($1, $2, $3, $4)
+            RETURNING context_id, user_id, objects, type,
+                      reference_count, allowed_count
+            ",
+            &[&context_id, user_id, &objects, &r#type.as_str()],
+        )
+        .await
+        .unwrap();
+    row_to_file_context(row)
+}
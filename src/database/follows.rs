@@ -0,0 +1,248 @@
+use deadpool_postgres::Transaction;
+use serde::Serialize;
+
+use crate::{database::conn::LazyConn, utils::storage::normalize_url};
+
+#[derive(Debug, Serialize)]
+pub struct FollowRequest {
+    pub user_id: String,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub created_at: i64,
+}
+
+/// Check if user_id follows target_id
+pub async fn is_following(user_id: &String, target_id: &String, conn: &mut LazyConn) -> bool {
+    let db = conn.get_client().await.unwrap();
+
+    let value = db
+        .query_opt(
+            "
+            SELECT 1 FROM followed
+            WHERE user_id = $1 AND followed_to = $2
+            LIMIT 1
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    value.is_some()
+}
+
+/// Follow user, returns false if already followed
+pub async fn follow_user(user_id: &String, target_id: &String, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            INSERT INTO followed (user_id, followed_to)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, followed_to) DO NOTHING
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Unfollow user, returns false if user wasn't followed
+pub async fn unfollow_user(
+    user_id: &String,
+    target_id: &String,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let affected = tx
+        .execute(
+            "
+            DELETE FROM followed
+            WHERE user_id = $1 AND followed_to = $2
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Create pending follow request, returns false if it already exists
+pub async fn create_follow_request(
+    user_id: &String,
+    target_id: &String,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let affected = tx
+        .execute(
+            "
+            INSERT INTO follow_requests (user_id, followed_to)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, followed_to) DO NOTHING
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Removes pending follow request, returns false if there was none
+/// Used for approving, rejecting and cancelling requests
+pub async fn remove_follow_request(
+    user_id: &String,
+    target_id: &String,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let affected = tx
+        .execute(
+            "
+            DELETE FROM follow_requests
+            WHERE user_id = $1 AND followed_to = $2
+            ",
+            &[user_id, target_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Turns all pending follow requests sent to user_id into follows
+/// Used when account stops being private, returns ids of new followers
+pub async fn approve_follow_requests(user_id: &String, tx: &mut Transaction<'_>) -> Vec<String> {
+    let rows = tx
+        .query(
+            "
+            WITH approved AS (
+                DELETE FROM follow_requests
+                WHERE followed_to = $1
+                RETURNING user_id
+            )
+            INSERT INTO followed (user_id, followed_to)
+            SELECT user_id, $1 FROM approved
+            ON CONFLICT DO NOTHING
+            RETURNING user_id
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(|r| r.get("user_id")).collect()
+}
+
+/// Get pending follow requests sent to user_id, newest first
+pub async fn get_follow_requests(user_id: &String, conn: &mut LazyConn) -> Vec<FollowRequest> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            SELECT u.user_id, u.username, p.display_name,
+                   ac.objects[1] AS avatar_url,
+                   EXTRACT(EPOCH FROM fr.created_at)::BIGINT AS created_at
+            FROM follow_requests fr
+            JOIN users u ON u.user_id = fr.user_id
+            LEFT JOIN user_profiles p ON p.user_id = u.user_id
+            LEFT JOIN files ac ON ac.context_id = p.avatar_context_id
+            WHERE fr.followed_to = $1
+            ORDER BY fr.created_at DESC
+            LIMIT 100
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+
+    rows.into_iter()
+        .map(|row| FollowRequest {
+            user_id: row.get("user_id"),
+            username: row.get("username"),
+            display_name: row.get("display_name"),
+            avatar_url: normalize_url(row.get("avatar_url")),
+            created_at: row.get("created_at"),
+        })
+        .collect()
+}
+
+/// Get ids of all followers of user
+pub async fn get_follower_ids(user_id: &String, conn: &mut LazyConn) -> Vec<String> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "SELECT user_id FROM followed WHERE followed_to = $1",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(|r| r.get("user_id")).collect()
+}
+
+/// Get followers count of user, 0 for unknown users
+pub async fn get_followers_count(user_id: &String, conn: &mut LazyConn) -> i64 {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "SELECT followers_count FROM users WHERE user_id = $1",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    row.map(|r| r.get("followers_count")).unwrap_or(0)
+}
+
+/// Get accounts followed by user that have at least 'min_followers' followers
+pub async fn get_large_followed(
+    user_id: &String,
+    min_followers: i64,
+    conn: &mut LazyConn,
+) -> Vec<String> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            SELECT f.followed_to FROM followed f
+            JOIN users u ON u.user_id = f.followed_to
+            WHERE f.user_id = $1 AND u.followers_count >= $2
+            ",
+            &[user_id, &min_followers],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(|r| r.get("followed_to")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::test_support::{cleanup, new_user},
+        get_conn,
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn follow_requests_are_approved_at_once() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let target = new_user(&mut conn).await;
+        let requester = new_user(&mut conn).await;
+        let follower = new_user(&mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        create_follow_request(&requester, &target, &mut tx).await;
+        follow_user(&follower, &target, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        let mut tx = create_tx!(conn);
+        assert_eq!(approve_follow_requests(&target, &mut tx).await, vec![requester.clone()]);
+        assert!(approve_follow_requests(&target, &mut tx).await.is_empty());
+        tx.commit().await.unwrap();
+
+        assert!(get_follow_requests(&target, &mut conn).await.is_empty());
+        assert!(is_following(&requester, &target, &mut conn).await);
+        assert_eq!(get_followers_count(&target, &mut conn).await, 2);
+
+        cleanup(&[&target, &requester, &follower], &mut conn).await;
+    }
+}
@@ -0,0 +1,127 @@
+use deadpool_postgres::Transaction;
+
+/// Replaces mentions of post or its comment with users of 'usernames'
+/// Unknown users, the author and users blocked either way are ignored
+/// Returns ids of newly mentioned users, so edits don't notify the same users again
+pub async fn set_mentions(
+    author_id: &String,
+    post_id: &String,
+    comment_id: Option<&String>,
+    usernames: &[String],
+    tx: &mut Transaction<'_>,
+) -> Vec<String> {
+    tx.execute(
+        "
+        DELETE FROM mentions
+        WHERE post_id = $1 AND comment_id IS NOT DISTINCT FROM $2
+          AND NOT (username = ANY($3))
+        ",
+        &[post_id, &comment_id, &usernames],
+    )
+    .await
+    .unwrap();
+    if usernames.is_empty() {
+        return Vec::new();
+    }
+
+    let rows = tx
+        .query(
+            "
+            INSERT INTO mentions (post_id, comment_id, user_id, username)
+            SELECT $1, $2, u.user_id, u.username FROM users u
+            WHERE u.username = ANY($3) AND u.user_id <> $4
+              AND NOT EXISTS (
+                  SELECT 1 FROM blocked_users b
+                  WHERE (b.user_id = $4 AND b.blocked_id = u.user_id)
+                     OR (b.user_id = u.user_id AND b.blocked_id = $4)
+              )
+            ON CONFLICT DO NOTHING
+            RETURNING user_id
+            ",
+            &[post_id, &comment_id, &usernames, author_id],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(|r| r.get("user_id")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::{
+            blocks::block_user,
+            conn::LazyConn,
+            posts::{create_post, get_post},
+            test_support::{cleanup, new_user},
+            users::get_min_user,
+        },
+        get_conn,
+        utils::{state::AppState, text::extract_mentions, thread_state::generate_id},
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn mentions_resolve_to_entities() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let friend = new_user(&mut conn).await;
+        let blocker = new_user(&mut conn).await;
+        let mut names = Vec::new();
+        for user_id in [&author, &friend, &blocker] {
+            names.push(get_min_user(user_id, &mut conn).await.unwrap().username);
+        }
+
+        let content = format!(
+            "@{} @{} @{} @nobody_{} #Rust https://example.com",
+            names[0],
+            names[1],
+            names[2],
+            generate_id()
+        );
+        let mut tx = create_tx!(conn);
+        block_user(&blocker, &author, &mut tx).await;
+        let post_id =
+            create_post(&author, &content, &None, &vec![], &None, "public", &mut tx).await;
+        let mentions = extract_mentions(&content);
+        let mentioned = set_mentions(&author, &post_id, None, &mentions, &mut tx).await;
+        assert_eq!(mentioned, vec![friend.clone()]);
+        // Same mentions on edit don't notify again
+        assert!(
+            set_mentions(&author, &post_id, None, &mentions, &mut tx)
+                .await
+                .is_empty()
+        );
+        tx.commit().await.unwrap();
+
+        let post = get_post(&post_id, &friend, &mut conn, &state, false)
+            .await
+            .unwrap();
+        let entities: Vec<(&str, &str, Option<&str>)> = post
+            .entities
+            .iter()
+            .map(|e| {
+                (
+                    e.r#type.as_str(),
+                    &content[e.start..e.end],
+                    e.user_id.as_deref(),
+                )
+            })
+            .collect();
+        let mention = format!("@{}", names[1]);
+        assert_eq!(
+            entities,
+            vec![
+                ("mention", mention.as_str(), Some(friend.as_str())),
+                ("hashtag", "#Rust", None),
+                ("url", "https://example.com", None),
+            ]
+        );
+
+        cleanup(&[&author, &friend, &blocker], &mut conn).await;
+    }
+}
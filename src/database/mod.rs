@@ -1,4 +1,26 @@
+pub mod audit;
 pub mod auth;
+pub mod badges;
+pub mod blocks;
+pub mod comments;
 pub mod conn;
-pub mod users;
+pub mod exports;
+pub mod favorites;
+pub mod files;
+pub mod follows;
+pub mod mentions;
+pub mod notifications;
+pub mod polls;
+pub mod popular;
 pub mod posts;
+pub mod presence;
+pub mod reactions;
+pub mod recommendations;
+pub mod roles;
+pub mod settings;
+pub mod tags;
+#[cfg(test)]
+pub mod test_support;
+pub mod timelines;
+pub mod users;
+pub mod views;
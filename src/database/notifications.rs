@@ -0,0 +1,53 @@
+use deadpool_postgres::Transaction;
+
+use crate::utils::thread_state::generate_id;
+
+/// Creates notification for user_id
+/// linked is (linked_type, linked_id), e.g. ("post", post_id)
+pub async fn create_notification(
+    user_id: &str,
+    from_id: &str,
+    r#type: &str,
+    linked: Option<(&str, &str)>,
+    tx: &mut Transaction<'_>,
+) -> String {
+    let id = generate_id().to_string();
+    let (linked_type, linked_id) = linked.unzip();
+
+    tx.execute(
+        "
+        INSERT INTO user_notifications (id, user_id, type, from_id, linked_type, linked_id)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ",
+        &[&id, &user_id, &r#type, &from_id, &linked_type, &linked_id],
+    )
+    .await
+    .unwrap();
+    id
+}
+
+/// Creates the same notification for every user in user_ids
+pub async fn create_notifications(
+    user_ids: &[String],
+    from_id: &str,
+    r#type: &str,
+    linked: Option<(&str, &str)>,
+    tx: &mut Transaction<'_>,
+) {
+    if user_ids.is_empty() {
+        return;
+    }
+    let ids: Vec<String> = user_ids.iter().map(|_| generate_id().to_string()).collect();
+    let (linked_type, linked_id) = linked.unzip();
+
+    tx.execute(
+        "
+        INSERT INTO user_notifications (id, user_id, type, from_id, linked_type, linked_id)
+        SELECT id, user_id, $3, $4, $5, $6
+        FROM unnest($1::TEXT[], $2::TEXT[]) AS n(id, user_id)
+        ",
+        &[&ids, &user_ids, &r#type, &from_id, &linked_type, &linked_id],
+    )
+    .await
+    .unwrap();
+}
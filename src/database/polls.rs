@@ -0,0 +1,275 @@
+use deadpool_postgres::Transaction;
+
+/// Creates poll of post, options are stored in given order
+pub async fn create_poll(
+    post_id: &String,
+    options: &[String],
+    multiple_choice: bool,
+    hide_results: bool,
+    expires_in: i64,
+    tx: &mut Transaction<'_>,
+) {
+    tx.execute(
+        "
+        INSERT INTO polls (post_id, multiple_choice, hide_results, expires_at)
+        VALUES ($1, $2, $3, now() + $4::BIGINT * INTERVAL '1 second')
+        ",
+        &[post_id, &multiple_choice, &hide_results, &expires_in],
+    )
+    .await
+    .unwrap();
+
+    let positions: Vec<i16> = (0..options.len() as i16).collect();
+    tx.execute(
+        "
+        INSERT INTO poll_options (post_id, position, text)
+        SELECT $1, * FROM unnest($2::SMALLINT[], $3::TEXT[])
+        ",
+        &[post_id, &positions, &options],
+    )
+    .await
+    .unwrap();
+}
+
+/// Stores vote of user and updates counts in the same transaction
+/// Options should be already checked against the poll
+/// Returns false if user already voted or poll expired
+pub async fn vote_poll(
+    post_id: &String,
+    user_id: &String,
+    options: &[i16],
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let affected = tx
+        .execute(
+            "
+            INSERT INTO poll_voters (post_id, user_id, options)
+            SELECT post_id, $2, $3 FROM polls
+            WHERE post_id = $1 AND expires_at > now()
+            ON CONFLICT (post_id, user_id) DO NOTHING
+            ",
+            &[post_id, user_id, &options],
+        )
+        .await
+        .unwrap();
+    if affected == 0 {
+        return false;
+    }
+
+    tx.execute(
+        "
+        UPDATE poll_options SET votes_count = votes_count + 1
+        WHERE post_id = $1 AND position = ANY($2)
+        ",
+        &[post_id, &options],
+    )
+    .await
+    .unwrap();
+    tx.execute(
+        "UPDATE polls SET voters_count = voters_count + 1 WHERE post_id = $1",
+        &[post_id],
+    )
+    .await
+    .unwrap();
+    true
+}
+
+/// Marks up to 'limit' expired polls of published posts as closed
+/// Locked rows are left for other servers
+/// Returns: (post_id, author_id, voter ids) of closed polls whose posts aren't deleted
+pub async fn close_expired_polls(
+    limit: i64,
+    tx: &mut Transaction<'_>,
+) -> Vec<(String, String, Vec<String>)> {
+    let rows = tx
+        .query(
+            "
+            WITH closed AS (
+                UPDATE polls SET closed_at = now()
+                WHERE post_id IN (
+                    SELECT pl.post_id FROM polls pl
+                    JOIN posts p ON p.post_id = pl.post_id
+                    WHERE pl.closed_at IS NULL AND pl.expires_at <= now()
+                      AND COALESCE(p.status, 'active') = 'active'
+                    ORDER BY pl.expires_at
+                    LIMIT $1
+                    FOR UPDATE OF pl SKIP LOCKED
+                )
+                RETURNING post_id
+            )
+            SELECT p.post_id, p.user_id,
+                   ARRAY(
+                       SELECT pv.user_id FROM poll_voters pv
+                       WHERE pv.post_id = p.post_id AND pv.user_id <> p.user_id
+                   ) AS voters
+            FROM closed c
+            JOIN posts p ON p.post_id = c.post_id
+            WHERE NOT COALESCE(p.is_deleted, FALSE)
+            ",
+            &[&limit],
+        )
+        .await
+        .unwrap();
+    rows.into_iter()
+        .map(|r| (r.get("post_id"), r.get("user_id"), r.get("voters")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::{
+            conn::LazyConn,
+            posts::{get_post, publish_post, set_post_schedule},
+            test_support::{cleanup, new_post, new_user},
+        },
+        get_conn,
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn polls_count_votes_and_close() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let voter = new_user(&mut conn).await;
+        let viewer = new_user(&mut conn).await;
+        let post_id = new_post(&author, &[], &mut conn).await;
+
+        let options = vec!["yes".to_string(), "no".to_string(), "maybe".to_string()];
+        let mut tx = create_tx!(conn);
+        create_poll(&post_id, &options, true, true, 3600, &mut tx).await;
+        assert!(vote_poll(&post_id, &voter, &[0, 2], &mut tx).await);
+        assert!(!vote_poll(&post_id, &voter, &[1], &mut tx).await);
+        tx.commit().await.unwrap();
+
+        // Results are hidden until viewer votes
+        let poll = get_post(&post_id, &viewer, &mut conn, &state, false)
+            .await
+            .unwrap()
+            .poll
+            .unwrap();
+        assert!(!poll.results_visible && poll.voters_count.is_none());
+        assert!(poll.options.iter().all(|o| o.votes_count.is_none()));
+
+        let poll = get_post(&post_id, &voter, &mut conn, &state, false)
+            .await
+            .unwrap()
+            .poll
+            .unwrap();
+        let counts: Vec<Option<i64>> = poll.options.iter().map(|o| o.votes_count).collect();
+        assert_eq!(counts, vec![Some(1), Some(0), Some(1)]);
+        assert_eq!((poll.voters_count, poll.votes_count), (Some(1), Some(2)));
+        assert_eq!(poll.own_vote, Some(vec![0, 2]));
+
+        let db = conn.get_client().await.unwrap();
+        db.execute(
+            "UPDATE polls SET expires_at = now() - INTERVAL '1 minute' WHERE post_id = $1",
+            &[&post_id],
+        )
+        .await
+        .unwrap();
+        let mut tx = create_tx!(conn);
+        assert!(!vote_poll(&post_id, &viewer, &[1], &mut tx).await);
+        tx.commit().await.unwrap();
+
+        // Closing notifies author and voters
+        crate::services::polls::close(state.clone()).await;
+        let db = conn.get_client().await.unwrap();
+        let rows = db
+            .query(
+                "
+                SELECT user_id FROM user_notifications
+                WHERE type = 'poll_closed' AND linked_id = $1
+                ORDER BY user_id
+                ",
+                &[&post_id],
+            )
+            .await
+            .unwrap();
+        let mut notified: Vec<String> = rows.iter().map(|r| r.get("user_id")).collect();
+        let mut expected = vec![author.clone(), voter.clone()];
+        notified.sort();
+        expected.sort();
+        assert_eq!(notified, expected);
+
+        let poll = get_post(&post_id, &viewer, &mut conn, &state, false)
+            .await
+            .unwrap()
+            .poll
+            .unwrap();
+        assert!(poll.closed && poll.results_visible);
+
+        cleanup(&[&author, &voter, &viewer], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn draft_polls_close_only_after_publish() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let draft = new_post(&author, &[], &mut conn).await;
+        let closed = new_post(&author, &[], &mut conn).await;
+
+        let options = vec!["yes".to_string(), "no".to_string()];
+        let mut tx = create_tx!(conn);
+        for post_id in [&draft, &closed] {
+            create_poll(post_id, &options, false, false, 3600, &mut tx).await;
+            set_post_schedule(post_id, None, &mut tx).await;
+        }
+        tx.execute(
+            "
+            UPDATE polls SET expires_at = now() - INTERVAL '1 minute',
+                closed_at = CASE WHEN post_id = $2 THEN now() END
+            WHERE post_id = ANY($1)
+            ",
+            &[&vec![draft.clone(), closed.clone()], &closed],
+        )
+        .await
+        .unwrap();
+        tx.execute(
+            "UPDATE posts SET created_at = now() - INTERVAL '61 minutes' WHERE post_id = ANY($1)",
+            &[&vec![draft.clone(), closed.clone()]],
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        crate::services::polls::close(state.clone()).await;
+        let db = conn.get_client().await.unwrap();
+        let open: i64 = db
+            .query_one(
+                "SELECT COUNT(*) FROM polls WHERE post_id = $1 AND closed_at IS NULL",
+                &[&draft],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(open, 1);
+
+        // Publishing reopens polls, they run for the full duration from publish time
+        let mut tx = create_tx!(conn);
+        assert!(publish_post(&draft, &mut tx).await);
+        assert!(publish_post(&closed, &mut tx).await);
+        tx.commit().await.unwrap();
+        for post_id in [&draft, &closed] {
+            let poll = get_post(post_id, &author, &mut conn, &state, false)
+                .await
+                .unwrap()
+                .poll
+                .unwrap();
+            assert!(!poll.closed);
+            assert!(poll.expires_at > Utc::now().timestamp() + 3000);
+        }
+
+        cleanup(&[&author], &mut conn).await;
+    }
+}
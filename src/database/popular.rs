@@ -0,0 +1,192 @@
+use fred::{
+    prelude::{Expiration, KeysInterface, SetOptions, SortedSetsInterface},
+    types::sorted_sets::AggregateOptions,
+};
+
+use crate::utils::state::ArcAppState;
+
+/// Amount of best ranked posts kept in snapshot
+pub const POPULAR_SIZE: i64 = 1000;
+/// Snapshots outlive refreshes, so cursors stay valid while client pages through
+const SNAPSHOT_TTL: i64 = 3600;
+
+const CURRENT_KEY: &str = "popular:current";
+const LOCK_KEY: &str = "popular:lock";
+
+/// Key of ranked snapshot, language snapshots only have posts of authors speaking it
+fn snapshot_key(snapshot: &str, language: Option<&str>) -> String {
+    match language {
+        Some(language) => format!("popular:{}:lang:{}", snapshot, language),
+        None => format!("popular:{}", snapshot),
+    }
+}
+
+/// Takes refresh lock for 'ttl' seconds, so only one server computes snapshot
+/// Returns false if lock is held by someone else or Redis is unavailable
+pub async fn acquire_refresh_lock(ttl: i64, state: &ArcAppState) -> bool {
+    let result: Result<Option<String>, _> = state
+        .cache_redis
+        .set(
+            LOCK_KEY,
+            "1",
+            Some(Expiration::EX(ttl)),
+            Some(SetOptions::NX),
+            false,
+        )
+        .await;
+    matches!(result, Ok(Some(_)))
+}
+
+/// Stores ranking as new snapshot and makes it current
+/// Ranking items are (post_id, score, languages of author)
+pub async fn store_snapshot(
+    snapshot: &str,
+    ranking: &[(String, f64, Vec<String>)],
+    state: &ArcAppState,
+) {
+    if ranking.is_empty() {
+        let _: Result<i64, _> = state.cache_redis.del(CURRENT_KEY).await;
+        return;
+    }
+
+    let mut by_language: Vec<(&str, Vec<(f64, &str)>)> = Vec::new();
+    for (post_id, score, languages) in ranking {
+        for language in languages {
+            match by_language.iter_mut().find(|(l, _)| l == language) {
+                Some((_, values)) => values.push((*score, post_id)),
+                None => by_language.push((language, vec![(*score, post_id)])),
+            }
+        }
+    }
+    let values: Vec<(f64, &str)> = ranking
+        .iter()
+        .map(|(post_id, score, _)| (*score, post_id.as_str()))
+        .collect();
+
+    let pipeline = state.cache_redis.pipeline();
+    let key = snapshot_key(snapshot, None);
+    let _: Result<(), _> = pipeline.zadd(&key, None, None, false, false, values).await;
+    let _: Result<(), _> = pipeline.expire(&key, SNAPSHOT_TTL, None).await;
+    for (language, values) in by_language {
+        let key = snapshot_key(snapshot, Some(language));
+        let _: Result<(), _> = pipeline.zadd(&key, None, None, false, false, values).await;
+        let _: Result<(), _> = pipeline.expire(&key, SNAPSHOT_TTL, None).await;
+    }
+    let _: Result<(), _> = pipeline
+        .set(
+            CURRENT_KEY,
+            snapshot,
+            Some(Expiration::EX(SNAPSHOT_TTL)),
+            None,
+            false,
+        )
+        .await;
+    let _: Result<(), _> = pipeline.all().await;
+}
+
+/// Get id of current snapshot, None if there is none yet
+pub async fn get_current_snapshot(state: &ArcAppState) -> Option<String> {
+    state.cache_redis.get(CURRENT_KEY).await.unwrap_or(None)
+}
+
+/// Get 'count' post ids of snapshot starting at 'offset', best first
+/// Posts are limited to authors speaking any of 'languages' if it isn't empty
+/// Returns None if snapshot expired
+pub async fn get_snapshot_page(
+    snapshot: &str,
+    languages: &[String],
+    offset: i64,
+    count: i64,
+    state: &ArcAppState,
+) -> Option<Vec<String>> {
+    let exists: i64 = state
+        .cache_redis
+        .exists(snapshot_key(snapshot, None))
+        .await
+        .ok()?;
+    if exists == 0 {
+        return None;
+    }
+
+    let key = match languages {
+        [] => snapshot_key(snapshot, None),
+        [language] => snapshot_key(snapshot, Some(language)),
+        _ => {
+            // Union is stored for the snapshot lifetime, so following pages read the same ranking
+            let mut languages = languages.to_vec();
+            languages.sort();
+            let key = snapshot_key(snapshot, Some(&languages.join(",")));
+            let exists: i64 = state.cache_redis.exists(&key).await.ok()?;
+            if exists == 0 {
+                let keys: Vec<String> = languages
+                    .iter()
+                    .map(|l| snapshot_key(snapshot, Some(l)))
+                    .collect();
+                let _: i64 = state
+                    .cache_redis
+                    .zunionstore(&key, keys, None, Some(AggregateOptions::Max))
+                    .await
+                    .ok()?;
+                let _: Result<(), _> = state.cache_redis.expire(&key, SNAPSHOT_TTL, None).await;
+            }
+            key
+        }
+    };
+    state
+        .cache_redis
+        .zrange(&key, offset, offset + count - 1, None, true, None, false)
+        .await
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::utils::{state::AppState, thread_state::generate_id};
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn snapshot_pages_follow_languages() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        let lang = |l: &str| vec![l.to_string()];
+        let ranking = vec![
+            ("1".to_string(), 3.0, lang("en")),
+            (
+                "2".to_string(),
+                2.0,
+                vec!["de".to_string(), "en".to_string()],
+            ),
+            ("3".to_string(), 1.0, lang("fr")),
+        ];
+        let snapshot = generate_id().to_string();
+        store_snapshot(&snapshot, &ranking, &state).await;
+        assert_eq!(get_current_snapshot(&state).await, Some(snapshot.clone()));
+
+        let page = |languages: Vec<String>, offset| {
+            let (state, snapshot) = (state.clone(), snapshot.clone());
+            async move { get_snapshot_page(&snapshot, &languages, offset, 2, &state).await }
+        };
+        let ids = |ids: &[&str]| Some(ids.iter().map(|i| i.to_string()).collect::<Vec<_>>());
+        assert_eq!(page(vec![], 0).await, ids(&["1", "2"]));
+        assert_eq!(page(vec![], 2).await, ids(&["3"]));
+        assert_eq!(page(lang("de"), 0).await, ids(&["2"]));
+        let languages = vec!["fr".to_string(), "de".to_string()];
+        assert_eq!(page(languages.clone(), 0).await, ids(&["2", "3"]));
+        // Union is kept for following pages
+        assert_eq!(page(languages, 1).await, ids(&["3"]));
+
+        assert_eq!(get_snapshot_page("0", &[], 0, 2, &state).await, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn refresh_lock_is_taken_once() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        let _: i64 = state.cache_redis.del(LOCK_KEY).await.unwrap();
+        assert!(acquire_refresh_lock(60, &state).await);
+        assert!(!acquire_refresh_lock(60, &state).await);
+        let _: i64 = state.cache_redis.del(LOCK_KEY).await.unwrap();
+    }
+}
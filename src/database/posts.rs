@@ -1,37 +1,230 @@
+use std::collections::HashMap;
+
+use deadpool_postgres::Transaction;
+use serde_json::Value;
 use tokio_postgres::Row;
 
 use crate::{
     database::conn::LazyConn,
-    entities::post::Post,
-    utils::{state::ArcAppState, storage::build_links},
+    entities::{
+        poll::Poll,
+        post::{ContentEntity, POST_TYPE_POST, POST_TYPE_QUOTE, Post, PostRevision},
+        reaction::Reaction,
+    },
+    utils::{
+        pagination::{CursorParams, TimeCursor, TimeCursorParams},
+        state::ArcAppState,
+        storage::build_links,
+        text::{EntityKind, extract_entities},
+        thread_state::generate_id,
+    },
 };
 
-pub static POST_SQL: &str = "
-    SELECT p.post_id, p.user_id, p.content,
-           EXTRACT(EPOCH FROM p.created_at),
-           EXTRACT(EPOCH FROM p.updated_at),
-           p.likes_count, p.comments_count,
-           p.dislikes_count, p.tags, m.objects as media,
-           m.type as media_type
+/// Minimal post info for ownership and deletion checks
+#[derive(Debug)]
+pub struct PostMeta {
+    pub user_id: String,
+    pub is_deleted: bool,
+    pub deleted_at: Option<i64>,
+    pub has_media: bool,
+    pub post_type: String,
+    /// Draft or scheduled post
+    pub is_draft: bool,
+}
+
+/// Columns of Post entity, queries are built from POST_COLUMNS and POST_FROM
+/// $1 is always the viewer
+const POST_COLUMNS: &str = "
+    p.post_id, p.user_id, p.content,
+           EXTRACT(EPOCH FROM p.created_at)::BIGINT AS created_at,
+           EXTRACT(EPOCH FROM p.updated_at)::BIGINT AS updated_at,
+           COALESCE(p.likes_count, 0) AS likes_count,
+           COALESCE(p.dislikes_count, 0) AS dislikes_count,
+           COALESCE(p.comments_count, 0) AS comments_count,
+           COALESCE(p.flags, '{}') AS flags,
+           COALESCE(m.objects, '{}') AS media,
+           m.type AS media_type,
+           p.status, p.is_deleted,
+           EXTRACT(EPOCH FROM p.publish_at)::BIGINT AS publish_at,
+           p.visibility,
            COALESCE(
                 array_agg(t.name)
                 FILTER (WHERE t.tag_id IS NOT NULL),
-                '{{}}'
-           ) AS ctags
-    FROM posts p
+                '{}'
+           ) AS tags,
+           (
+                SELECT r.is_like FROM reactions r
+                WHERE r.post_id = p.post_id AND r.comment_id IS NULL
+                  AND r.user_id = $1
+           ) AS reaction,
+           EXISTS (
+                SELECT 1 FROM favorites fv
+                WHERE fv.post_id = p.post_id AND fv.comment_id IS NULL
+                  AND fv.user_id = $1
+           ) AS is_favorited,
+           p.revisions_count, p.post_type, p.reference_post_id,
+           p.reposts_count, p.quotes_count,
+           EXISTS (
+                SELECT 1 FROM posts rp
+                WHERE rp.reference_post_id = p.post_id AND rp.post_type = 'repost'
+                  AND rp.user_id = $1
+           ) AS is_reposted,
+           (
+                SELECT json_build_object(
+                    'multiple_choice', pl.multiple_choice,
+                    'hide_results', pl.hide_results,
+                    'expires_at', EXTRACT(EPOCH FROM pl.expires_at)::BIGINT,
+                    'closed', pl.expires_at <= now(),
+                    'results_visible', NOT pl.hide_results OR pl.expires_at <= now()
+                        OR pv.user_id IS NOT NULL OR p.user_id = $1,
+                    'voters_count', pl.voters_count,
+                    'own_vote', pv.options,
+                    'options', (
+                        SELECT json_agg(
+                            json_build_object('text', po.text, 'votes_count', po.votes_count)
+                            ORDER BY po.position
+                        )
+                        FROM poll_options po WHERE po.post_id = pl.post_id
+                    )
+                )
+                FROM polls pl
+                LEFT JOIN poll_voters pv ON pv.post_id = pl.post_id AND pv.user_id = $1
+                WHERE pl.post_id = p.post_id
+           ) AS poll,
+           (
+                SELECT json_object_agg(mn.username, mn.user_id) FROM mentions mn
+                WHERE mn.post_id = p.post_id AND mn.comment_id IS NULL
+           ) AS mentions
+";
+
+const POST_FROM: &str = "
+    posts p
     LEFT JOIN post_tags pt ON pt.post_id = p.post_id
     LEFT JOIN tags t ON t.tag_id = pt.tag_id
     LEFT JOIN files m ON m.context_id = p.file_context_id
+    LEFT JOIN user_profiles up ON up.user_id = p.user_id
+";
+
+/// Author always sees own posts, others see them by post visibility:
+/// public and unlisted to everyone, followers to followers, friends to author's friends
+/// Posts of private accounts are only visible to approved followers on top of that
+/// Drafts and scheduled posts aren't visible anywhere, author gets them from get_drafts
+const POST_VISIBLE: &str = "
+    COALESCE(p.status, 'active') NOT IN ('draft', 'scheduled')
+    AND (
+        p.user_id = $1
+        OR (
+            (
+                NOT COALESCE(up.is_private, FALSE)
+                OR EXISTS (
+                    SELECT 1 FROM followed f
+                    WHERE f.user_id = $1 AND f.followed_to = p.user_id
+                )
+            )
+            AND (
+                p.visibility IN ('public', 'unlisted')
+                OR (p.visibility = 'followers' AND EXISTS (
+                    SELECT 1 FROM followed f
+                    WHERE f.user_id = $1 AND f.followed_to = p.user_id
+                ))
+                OR (p.visibility = 'friends' AND EXISTS (
+                    SELECT 1 FROM friends fr
+                    WHERE fr.user_id = p.user_id AND fr.friend_id = $1
+                ))
+            )
+        )
+    )
+";
 
-    WHERE (
-        ($1 AND p.post_id = $2)
-        OR
-        (NOT $1 AND p.user_id = $2)
-    ) AND ($3::bool OR p.is_deleted = false)
+/// Unlisted posts are left out of search and tag pages, except for the author
+const POST_LISTED: &str = "(p.visibility <> 'unlisted' OR p.user_id = $1)";
 
-    GROUP BY p.post_id, m.objects, m.type
+/// Posts of users that viewer muted or that are blocked either way are hidden in feeds
+/// Other's posts are also hidden by content_filters of viewer's settings,
+/// sensitive posts are hidden by default, same as in ContentFilters::default
+const POST_NOT_FILTERED: &str = "
+    NOT EXISTS (
+        SELECT 1 FROM blocked_users b
+        WHERE (b.user_id = $1 AND b.blocked_id = p.user_id)
+           OR (b.user_id = p.user_id AND b.blocked_id = $1)
+    )
+    AND NOT EXISTS (
+        SELECT 1 FROM muted_users mu
+        WHERE mu.user_id = $1 AND mu.muted_id = p.user_id
+    )
+    AND (
+        p.user_id = $1
+        OR (
+            NOT (
+                'sensitive' = ANY(COALESCE(p.flags, '{}'))
+                AND COALESCE((
+                    SELECT (us.settings #>> '{content_filters,hide_sensitive}')::BOOLEAN
+                    FROM user_settings us WHERE us.user_id = $1
+                ), TRUE)
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM user_settings us,
+                     jsonb_array_elements_text(us.settings #> '{content_filters,muted_words}') w
+                WHERE us.user_id = $1 AND strpos(lower(p.content), lower(w)) > 0
+            )
+        )
+    )
 ";
 
+const POST_GROUP: &str = "GROUP BY p.post_id, m.objects, m.type";
+
+/// Position of post in feeds, posts published from drafts are placed by their publish time
+const POST_FEED_ID: &str = "COALESCE(p.published_id, p.post_id::bigint)";
+
+/// Private function for feed position of post id in param $n
+/// Clients page feeds by post ids, unknown ids (e.g. of purged posts) are used as they are
+fn feed_position(n: usize) -> String {
+    format!(
+        "COALESCE((
+            SELECT COALESCE(fp.published_id, fp.post_id::bigint) FROM posts fp
+            WHERE fp.post_id = ${0}::BIGINT::TEXT
+        ), ${0}::BIGINT)",
+        n
+    )
+}
+
+/// Private function for cursor condition of feeds, takes params $n (before) and $n+1 (after)
+fn feed_where_clause(n: usize) -> String {
+    format!(
+        "(${0}::BIGINT IS NULL OR {2} < {3}) AND (${1}::BIGINT IS NULL OR {2} > {4})",
+        n,
+        n + 1,
+        POST_FEED_ID,
+        feed_position(n),
+        feed_position(n + 1)
+    )
+}
+
+/// Private function that builds entities of content, mentions are resolved with
+/// 'mentions' map of username to user_id, mentions of unknown or blocked users are left out
+fn content_entities(content: &str, mentions: Option<Value>) -> Vec<ContentEntity> {
+    let mentions: HashMap<String, String> = mentions
+        .and_then(|m| serde_json::from_value(m).ok())
+        .unwrap_or_default();
+    extract_entities(content)
+        .into_iter()
+        .filter_map(|e| {
+            let (r#type, user_id) = match e.kind {
+                EntityKind::Mention => ("mention", Some(mentions.get(&e.value)?.clone())),
+                EntityKind::Hashtag => ("hashtag", None),
+                EntityKind::Url => ("url", None),
+            };
+            Some(ContentEntity {
+                r#type: r#type.to_string(),
+                start: e.start,
+                end: e.end,
+                value: e.value,
+                user_id,
+            })
+        })
+        .collect()
+}
+
 /// Private function to get Post entity from Row
 /// Row needs to have all the non-option fields of Post
 fn row_to_post(row: Row, state: &ArcAppState) -> Post {
@@ -48,34 +241,1932 @@ fn row_to_post(row: Row, state: &ArcAppState) -> Post {
         media: build_links(row.get("media"), state),
         media_type: row.get("media_type"),
         status: row.get("status"),
+        publish_at: row.get("publish_at"),
+        visibility: row.get("visibility"),
         is_deleted: row.get("is_deleted"),
         tags: row.get("tags"),
+        reaction: row
+            .get::<_, Option<bool>>("reaction")
+            .map(Reaction::from_is_like),
+        is_favorited: row.get("is_favorited"),
+        revisions_count: row.get("revisions_count"),
+        edited: row.get::<_, i32>("revisions_count") > 0,
+        post_type: row.get("post_type"),
+        reference_post_id: row.get("reference_post_id"),
+        reference: None,
+        reposts_count: row.get("reposts_count"),
+        quotes_count: row.get("quotes_count"),
+        is_reposted: row.get("is_reposted"),
+        poll: row
+            .get::<_, Option<Value>>("poll")
+            .and_then(|poll| serde_json::from_value::<Poll>(poll).ok())
+            .map(Poll::with_totals),
+        entities: content_entities(row.get("content"), row.get("mentions")),
     }
 }
 
-pub enum PostLookup<'a> {
-    ByPostId(&'a str),
-    ByUserId(&'a str),
+/// Private function that embeds originals of reposts and quotes, only one level deep
+/// Originals that are deleted, moderated or hidden from viewer are left out,
+/// so such posts only have 'reference_post_id'
+async fn attach_references(
+    mut posts: Vec<Post>,
+    viewer_id: &str,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let ids: Vec<String> = posts
+        .iter()
+        .filter_map(|p| p.reference_post_id.clone())
+        .collect();
+    if ids.is_empty() {
+        return posts;
+    }
+
+    let references = fetch_posts_by_ids(viewer_id, &ids, conn, state).await;
+    for post in posts.iter_mut() {
+        post.reference = references
+            .iter()
+            .find(|r| post.reference_post_id.as_ref() == Some(&r.post_id))
+            .map(|r| Box::new(r.clone()));
+    }
+    posts
 }
 
-/// Get single post by id from database
-/// Returns: Post entity without 'is_deleted' and 'status' fields
+/// Get single post by id as seen by viewer
 pub async fn get_post(
-    lookup: PostLookup<'_>,
+    post_id: &str,
+    viewer_id: &str,
     conn: &mut LazyConn,
     state: &ArcAppState,
     include_deleted: bool,
 ) -> Option<Post> {
     let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {} FROM {}
+        WHERE {} AND p.post_id = $2 AND ($3 OR NOT COALESCE(p.is_deleted, FALSE)) {}",
+        POST_COLUMNS, POST_FROM, POST_VISIBLE, POST_GROUP
+    );
 
-    let (is_post, id) = match lookup {
-        PostLookup::ByPostId(id) => (true, id),
-        PostLookup::ByUserId(id) => (false, id),
-    };
+    let row = db
+        .query_opt(&sql, &[&viewer_id, &post_id, &include_deleted])
+        .await
+        .unwrap();
+    let post = row.map(|r| row_to_post(r, state))?;
+    attach_references(vec![post], viewer_id, conn, state)
+        .await
+        .pop()
+}
+
+/// Filters users down to those who can see the post
+pub async fn filter_post_viewers(
+    post_id: &str,
+    user_ids: &[String],
+    conn: &mut LazyConn,
+) -> Vec<String> {
+    let db = conn.get_client().await.unwrap();
+    // Visibility rule is checked for every user in place of viewer
+    let sql = format!(
+        "SELECT v.user_id FROM unnest($1::TEXT[]) AS v(user_id)
+        JOIN posts p ON p.post_id = $2
+        LEFT JOIN user_profiles up ON up.user_id = p.user_id
+        WHERE {} AND NOT COALESCE(p.is_deleted, FALSE)",
+        POST_VISIBLE.replace("$1", "v.user_id")
+    );
+
+    db.query(&sql, &[&user_ids, &post_id])
+        .await
+        .unwrap()
+        .iter()
+        .map(|r| r.get(0))
+        .collect()
+}
+
+/// Get page of user's posts as seen by viewer, newest first
+/// Deleted posts are never included
+pub async fn get_user_posts(
+    user_id: &str,
+    viewer_id: &str,
+    cursor: &CursorParams,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {} FROM {}
+        WHERE {} AND p.user_id = $2 AND NOT COALESCE(p.is_deleted, FALSE) AND {} {} {}",
+        POST_COLUMNS,
+        POST_FROM,
+        POST_VISIBLE,
+        feed_where_clause(3),
+        POST_GROUP,
+        cursor.order_clause(POST_FEED_ID, 5),
+    );
+
+    let rows = db
+        .query(
+            &sql,
+            &[
+                &viewer_id,
+                &user_id,
+                &cursor.before,
+                &cursor.after,
+                &cursor.limit(),
+            ],
+        )
+        .await
+        .unwrap();
+    let posts = rows.into_iter().map(|r| row_to_post(r, state)).collect();
+    cursor.arrange(attach_references(posts, viewer_id, conn, state).await)
+}
+
+/// Get page of posts with tag as seen by viewer, newest first
+pub async fn get_tag_posts(
+    tag_id: &str,
+    viewer_id: &str,
+    cursor: &CursorParams,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {} FROM {}
+        WHERE {} AND {} AND {} AND NOT COALESCE(p.is_deleted, FALSE)
+        AND EXISTS (SELECT 1 FROM post_tags tp WHERE tp.post_id = p.post_id AND tp.tag_id = $2)
+        AND {} {} {}",
+        POST_COLUMNS,
+        POST_FROM,
+        POST_VISIBLE,
+        POST_LISTED,
+        POST_NOT_FILTERED,
+        feed_where_clause(3),
+        POST_GROUP,
+        cursor.order_clause(POST_FEED_ID, 5),
+    );
+
+    let rows = db
+        .query(
+            &sql,
+            &[
+                &viewer_id,
+                &tag_id,
+                &cursor.before,
+                &cursor.after,
+                &cursor.limit(),
+            ],
+        )
+        .await
+        .unwrap();
+    let posts = rows.into_iter().map(|r| row_to_post(r, state)).collect();
+    cursor.arrange(attach_references(posts, viewer_id, conn, state).await)
+}
+
+/// Full-text search query with filters, see utils::text::split_search_query
+#[derive(Debug, Default)]
+pub struct PostSearch {
+    /// Web search syntax: words, "phrases", OR and -exclusions
+    pub text: String,
+    /// Words matched by prefix, only word characters
+    pub prefixes: Vec<String>,
+    /// Language of query, stems match besides exact words
+    pub language: Option<String>,
+    pub author_id: Option<String>,
+    /// Normalized tag name
+    pub tag: Option<String>,
+    /// Unix time bounds of creation, inclusive
+    pub since: Option<i64>,
+    pub until: Option<i64>,
+    pub has_media: Option<bool>,
+    pub offset: i64,
+    pub limit: i64,
+}
+
+/// Search posts as seen by viewer, best matches first
+/// Text relevance is boosted by popularity, deleted and moderated posts are never included
+pub async fn search_posts(
+    viewer_id: &str,
+    search: &PostSearch,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "WITH q AS (
+            SELECT (
+                websearch_to_tsquery('simple', $2)
+                || websearch_to_tsquery(search_config($3), $2)
+            ) && to_tsquery(
+                'simple',
+                array_to_string(ARRAY(SELECT w || ':*' FROM unnest($4::TEXT[]) w), ' & ')
+            ) AS query
+        )
+        SELECT {},
+               ts_rank_cd(p.search_vector, q.query)
+               * (1 + 0.2 * ln(1 + GREATEST(COALESCE(p.popularity_score, 0), 0))) AS rank
+        FROM {}
+        CROSS JOIN q
+        WHERE {} AND {} AND {} AND NOT COALESCE(p.is_deleted, FALSE)
+        AND COALESCE(p.status, 'active') = 'active'
+        AND p.search_vector @@ q.query
+        AND ($5::TEXT IS NULL OR p.user_id = $5)
+        AND ($6::TEXT IS NULL OR EXISTS (
+            SELECT 1 FROM post_tags tp
+            JOIN tags tt ON tt.tag_id = tp.tag_id
+            WHERE tp.post_id = p.post_id AND tt.name = $6
+        ))
+        AND ($7::BIGINT IS NULL OR p.created_at >= to_timestamp($7))
+        AND ($8::BIGINT IS NULL OR p.created_at <= to_timestamp($8))
+        AND ($9::BOOLEAN IS NULL OR (p.file_context_id IS NOT NULL) = $9)
+        {}, q.query
+        ORDER BY rank DESC, p.post_id::bigint DESC
+        OFFSET $10 LIMIT $11",
+        POST_COLUMNS, POST_FROM, POST_VISIBLE, POST_LISTED, POST_NOT_FILTERED, POST_GROUP
+    );
+
+    let rows = db
+        .query(
+            &sql,
+            &[
+                &viewer_id,
+                &search.text,
+                &search.language,
+                &search.prefixes,
+                &search.author_id,
+                &search.tag,
+                &search.since,
+                &search.until,
+                &search.has_media,
+                &search.offset,
+                &search.limit,
+            ],
+        )
+        .await
+        .unwrap();
+    let posts = rows.into_iter().map(|r| row_to_post(r, state)).collect();
+    attach_references(posts, viewer_id, conn, state).await
+}
+
+/// Get page of posts that viewer saved to favorites, most recently saved first
+/// Returns: posts with cursor of each one
+pub async fn get_favorite_posts(
+    viewer_id: &str,
+    cursor: &TimeCursorParams,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<(TimeCursor, Post)> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {}, {} AS saved_at FROM {}
+        JOIN favorites fav ON fav.post_id = p.post_id
+         AND fav.comment_id IS NULL AND fav.user_id = $1
+        WHERE {} AND NOT COALESCE(p.is_deleted, FALSE) AND {}
+        {}, fav.created_at
+        ORDER BY saved_at DESC, p.post_id::bigint DESC
+        LIMIT $4",
+        POST_COLUMNS,
+        TimeCursor::sql_value("fav.created_at"),
+        POST_FROM,
+        POST_VISIBLE,
+        TimeCursor::where_clause("fav.created_at", "p.post_id", 2),
+        POST_GROUP,
+    );
+
+    let (at, id) = cursor.before.map(|c| (c.at, c.id)).unzip();
+    let rows = db
+        .query(&sql, &[&viewer_id, &at, &id, &cursor.limit()])
+        .await
+        .unwrap();
+    let (cursors, posts): (Vec<TimeCursor>, Vec<Post>) = rows
+        .into_iter()
+        .map(|r| {
+            let post_cursor = TimeCursor {
+                at: r.get("saved_at"),
+                id: r.get::<_, String>("post_id").parse().unwrap(),
+            };
+            (post_cursor, row_to_post(r, state))
+        })
+        .unzip();
+    let posts = attach_references(posts, viewer_id, conn, state).await;
+    cursors.into_iter().zip(posts).collect()
+}
+
+/// Where posts of home feed page come from
+#[derive(Debug, Default)]
+pub struct HomeSource {
+    /// Post ids from materialised timeline
+    pub post_ids: Vec<String>,
+    /// Followed accounts that are too large for fan-out, merged at read time
+    pub large_ids: Vec<String>,
+    /// Id of the oldest post taken from timeline
+    pub oldest: Option<i64>,
+    /// Timeline has no more posts, older ones are read from followed accounts directly
+    pub exhausted: bool,
+}
+
+/// Get page of home feed, newest first
+/// 'before' is feed_id of post that client paged from, see get_feed_id
+pub async fn get_home_posts(
+    viewer_id: &str,
+    source: &HomeSource,
+    before: Option<i64>,
+    limit: i64,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {columns} FROM {from}
+        WHERE {visible} AND {not_filtered} AND NOT COALESCE(p.is_deleted, FALSE)
+        AND ($6::BIGINT IS NULL OR {feed_id} < $6)
+        AND (
+            p.post_id = ANY($2)
+            -- large accounts can't go past timeline page, otherwise timeline posts are skipped
+            OR (p.user_id = ANY($3) AND ($5 OR $4::BIGINT IS NULL OR {feed_id} >= {oldest}))
+            OR (
+                $5 AND ($4::BIGINT IS NULL OR {feed_id} < {oldest})
+                AND (
+                    p.user_id = $1
+                    OR p.user_id IN (SELECT followed_to FROM followed WHERE user_id = $1)
+                )
+            )
+        )
+        {group}
+        ORDER BY {feed_id} DESC
+        LIMIT $7",
+        columns = POST_COLUMNS,
+        from = POST_FROM,
+        visible = POST_VISIBLE,
+        not_filtered = POST_NOT_FILTERED,
+        group = POST_GROUP,
+        feed_id = POST_FEED_ID,
+        oldest = feed_position(4),
+    );
+
+    let rows = db
+        .query(
+            &sql,
+            &[
+                &viewer_id,
+                &source.post_ids,
+                &source.large_ids,
+                &source.oldest,
+                &source.exhausted,
+                &before,
+                &limit,
+            ],
+        )
+        .await
+        .unwrap();
+    let posts = rows.into_iter().map(|r| row_to_post(r, state)).collect();
+    attach_references(posts, viewer_id, conn, state).await
+}
+
+/// Get newest posts for building user's timeline
+/// Only own posts and posts of accounts below 'fanout_limit' followers are included
+/// Returns: (post_id, feed_id) of posts, newest first
+pub async fn get_timeline_seed(
+    user_id: &String,
+    fanout_limit: i64,
+    limit: i64,
+    conn: &mut LazyConn,
+) -> Vec<(String, i64)> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT p.post_id, {0} AS feed_id FROM posts p
+        WHERE NOT COALESCE(p.is_deleted, FALSE)
+        AND COALESCE(p.status, 'active') NOT IN ('draft', 'scheduled')
+        AND (
+            p.user_id = $1
+            OR p.user_id IN (
+                SELECT f.followed_to FROM followed f
+                JOIN users u ON u.user_id = f.followed_to
+                WHERE f.user_id = $1 AND u.followers_count < $2
+            )
+        )
+        ORDER BY {0} DESC
+        LIMIT $3",
+        POST_FEED_ID
+    );
+    let rows = db
+        .query(&sql, &[user_id, &fanout_limit, &limit])
+        .await
+        .unwrap();
+    rows.into_iter()
+        .map(|r| (r.get("post_id"), r.get("feed_id")))
+        .collect()
+}
+
+/// Get position of post in feeds, None if post doesn't exist
+pub async fn get_feed_id(post_id: &str, conn: &mut LazyConn) -> Option<i64> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {} AS feed_id FROM posts p WHERE p.post_id = $1",
+        POST_FEED_ID
+    );
+    let row = db.query_opt(&sql, &[&post_id]).await.unwrap();
+    row.map(|r| r.get("feed_id"))
+}
+
+/// Get best ranked posts created in the last 'window_hours', best first
+/// Score decays with age: (popularity + 1) / (age in hours + 2) ^ gravity
+/// Only public posts of public accounts are ranked, snapshot is shared by all viewers
+/// Returns: (post_id, score, languages of author)
+pub async fn get_popular_ranking(
+    gravity: f64,
+    window_hours: i64,
+    limit: i64,
+    conn: &mut LazyConn,
+) -> Vec<(String, f64, Vec<String>)> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            SELECT p.post_id,
+                   (GREATEST(COALESCE(p.popularity_score, 0), 0) + 1)::FLOAT8 / POWER(
+                        EXTRACT(EPOCH FROM now() - p.created_at)::FLOAT8 / 3600 + 2,
+                        $1::FLOAT8
+                   ) AS score,
+                   COALESCE(up.languages, '{}') AS languages
+            FROM posts p
+            LEFT JOIN user_profiles up ON up.user_id = p.user_id
+            WHERE NOT COALESCE(p.is_deleted, FALSE)
+              AND COALESCE(p.status, 'active') NOT IN ('draft', 'scheduled')
+              AND p.visibility = 'public'
+              AND p.post_type <> 'repost'
+              AND NOT COALESCE(up.is_private, FALSE)
+              AND p.created_at > now() - $2::BIGINT * INTERVAL '1 hour'
+            ORDER BY score DESC
+            LIMIT $3
+            ",
+            &[&gravity, &window_hours, &limit],
+        )
+        .await
+        .unwrap();
+    rows.into_iter()
+        .map(|r| (r.get("post_id"), r.get("score"), r.get("languages")))
+        .collect()
+}
+
+/// Get "for you" ranking of viewer, best first
+/// Candidates are recent posts of followed accounts, 'popular_ids' and recent posts
+/// with tags of posts viewer liked or saved, score of candidate from several sources adds up
+/// Own, seen, invisible, unlisted and filtered posts and reposts are left out
+/// Returns: (post_id, score)
+pub async fn get_recommended_ranking(
+    viewer_id: &str,
+    popular_ids: &[String],
+    gravity: f64,
+    window_hours: i64,
+    limit: i64,
+    conn: &mut LazyConn,
+) -> Vec<(String, f64)> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "
+        WITH interests AS (
+            SELECT pt.tag_id, COUNT(*) AS weight
+            FROM post_tags pt
+            WHERE pt.post_id IN (
+                SELECT post_id FROM reactions
+                WHERE user_id = $1 AND comment_id IS NULL AND is_like
+                  AND created_at > now() - INTERVAL '30 days'
+                UNION
+                SELECT post_id FROM favorites
+                WHERE user_id = $1 AND comment_id IS NULL
+                  AND created_at > now() - INTERVAL '30 days'
+            )
+            GROUP BY pt.tag_id
+            ORDER BY weight DESC
+            LIMIT 20
+        ),
+        candidates AS (
+            SELECT p.post_id, 3.0::FLOAT8 AS boost
+            FROM posts p
+            WHERE p.user_id IN (SELECT followed_to FROM followed WHERE user_id = $1)
+              AND p.created_at > now() - $4::BIGINT * INTERVAL '1 hour'
+            UNION ALL
+            SELECT unnest($2::TEXT[]), 2.0::FLOAT8
+            UNION ALL
+            SELECT pt.post_id, (1 + ln(1 + i.weight))::FLOAT8
+            FROM interests i
+            JOIN post_tags pt ON pt.tag_id = i.tag_id
+            JOIN posts p ON p.post_id = pt.post_id
+            WHERE p.created_at > now() - $4::BIGINT * INTERVAL '1 hour'
+        ),
+        scored AS (
+            SELECT post_id, SUM(boost) AS boost FROM candidates GROUP BY post_id
+        )
+        SELECT p.post_id,
+               s.boost * (GREATEST(COALESCE(p.popularity_score, 0), 0) + 1)::FLOAT8 / POWER(
+                    EXTRACT(EPOCH FROM now() - p.created_at)::FLOAT8 / 3600 + 2,
+                    $3::FLOAT8
+               ) AS score
+        FROM scored s
+        JOIN posts p ON p.post_id = s.post_id
+        LEFT JOIN user_profiles up ON up.user_id = p.user_id
+        WHERE p.user_id <> $1 AND NOT COALESCE(p.is_deleted, FALSE)
+          AND p.post_type <> 'repost' AND p.visibility <> 'unlisted'
+          AND {} AND {}
+          AND NOT EXISTS (
+              SELECT 1 FROM user_post_views v
+              WHERE v.user_id = $1 AND v.post_id = p.post_id
+          )
+        ORDER BY score DESC, p.post_id::bigint DESC
+        LIMIT $5
+        ",
+        POST_VISIBLE, POST_NOT_FILTERED
+    );
+
+    let rows = db
+        .query(
+            &sql,
+            &[&viewer_id, &popular_ids, &gravity, &window_hours, &limit],
+        )
+        .await
+        .unwrap();
+    rows.into_iter()
+        .map(|r| (r.get("post_id"), r.get("score")))
+        .collect()
+}
 
+/// Private function to get posts by ids without embedded originals
+async fn fetch_posts_by_ids(
+    viewer_id: &str,
+    post_ids: &[String],
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {} FROM {}
+        WHERE {} AND {} AND NOT COALESCE(p.is_deleted, FALSE)
+        AND COALESCE(p.status, 'active') = 'active'
+        AND p.post_id = ANY($2)
+        {}
+        ORDER BY array_position($2, p.post_id)",
+        POST_COLUMNS, POST_FROM, POST_VISIBLE, POST_NOT_FILTERED, POST_GROUP
+    );
+
+    let rows = db.query(&sql, &[&viewer_id, &post_ids]).await.unwrap();
+    rows.into_iter().map(|r| row_to_post(r, state)).collect()
+}
+
+/// Get posts by ids as seen by viewer, in the order of 'post_ids'
+/// Deleted, moderated, invisible and filtered posts are left out
+pub async fn get_posts_by_ids(
+    viewer_id: &str,
+    post_ids: &[String],
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let posts = fetch_posts_by_ids(viewer_id, post_ids, conn, state).await;
+    attach_references(posts, viewer_id, conn, state).await
+}
+
+/// Get author and deletion state of post, deleted posts are included
+pub async fn get_post_meta(post_id: &String, conn: &mut LazyConn) -> Option<PostMeta> {
+    let db = conn.get_client().await.unwrap();
     let row = db
-        .query_opt(POST_SQL, &[&is_post, &id, &include_deleted])
+        .query_opt(
+            "
+            SELECT user_id, COALESCE(is_deleted, FALSE) AS is_deleted,
+                   EXTRACT(EPOCH FROM deleted_at)::BIGINT AS deleted_at,
+                   file_context_id IS NOT NULL AS has_media, post_type,
+                   COALESCE(status IN ('draft', 'scheduled'), FALSE) AS is_draft
+            FROM posts
+            WHERE post_id = $1
+            ",
+            &[post_id],
+        )
         .await
         .unwrap();
+    row.map(|r| PostMeta {
+        user_id: r.get("user_id"),
+        is_deleted: r.get("is_deleted"),
+        deleted_at: r.get("deleted_at"),
+        has_media: r.get("has_media"),
+        post_type: r.get("post_type"),
+        is_draft: r.get("is_draft"),
+    })
+}
+
+/// Creates post, returns its id
+/// Post quoting 'quote_post_id' is created as quote, original should be already checked
+/// Tags are set separately with set_post_tags in the same transaction
+pub async fn create_post(
+    user_id: &String,
+    content: &String,
+    file_context_id: &Option<String>,
+    flags: &Vec<String>,
+    quote_post_id: &Option<String>,
+    visibility: &str,
+    tx: &mut Transaction<'_>,
+) -> String {
+    let post_id = generate_id().to_string();
+    let post_type = match quote_post_id {
+        Some(_) => POST_TYPE_QUOTE,
+        None => POST_TYPE_POST,
+    };
+    tx.execute(
+        "
+        INSERT INTO posts (
+            post_id, user_id, content, file_context_id, flags, post_type, reference_post_id,
+            visibility
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ",
+        &[
+            &post_id,
+            user_id,
+            content,
+            file_context_id,
+            flags,
+            &post_type,
+            quote_post_id,
+            &visibility,
+        ],
+    )
+    .await
+    .unwrap();
+    post_id
+}
+
+/// Reposts original, deleted repost of the same post is restored instead
+/// Returns id of repost, None if user already reposted it
+pub async fn create_repost(
+    user_id: &String,
+    reference_post_id: &String,
+    tx: &mut Transaction<'_>,
+) -> Option<String> {
+    let post_id = generate_id().to_string();
+    let row = tx
+        .query_opt(
+            "
+            INSERT INTO posts (post_id, user_id, content, post_type, reference_post_id)
+            VALUES ($1, $2, '', 'repost', $3)
+            ON CONFLICT (user_id, reference_post_id) WHERE post_type = 'repost'
+            DO UPDATE SET is_deleted = FALSE
+            WHERE COALESCE(posts.is_deleted, FALSE)
+            RETURNING post_id
+            ",
+            &[&post_id, user_id, reference_post_id],
+        )
+        .await
+        .unwrap();
+    row.map(|r| r.get("post_id"))
+}
+
+/// Removes repost of user, 'post_id' can be either the original or the repost itself
+/// Reposts have nothing to restore, so they are deleted right away
+/// Returns false if there was no repost
+pub async fn delete_repost(user_id: &String, post_id: &String, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            DELETE FROM posts
+            WHERE user_id = $1 AND post_type = 'repost'
+              AND (reference_post_id = $2 OR post_id = $2)
+            ",
+            &[user_id, post_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Updates content, flags and visibility of post, None fields are left as is
+/// Returns false if post doesn't exist or is deleted
+pub async fn update_post(
+    post_id: &String,
+    content: &Option<String>,
+    flags: &Option<Vec<String>>,
+    visibility: &Option<String>,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let affected = tx
+        .execute(
+            "
+            UPDATE posts
+            SET content = COALESCE($2, content),
+                flags = COALESCE($3, flags),
+                visibility = COALESCE($4, visibility)
+            WHERE post_id = $1 AND NOT COALESCE(is_deleted, FALSE)
+            ",
+            &[post_id, content, flags, visibility],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Replaces tags of post, missing tags are created
+/// Tag names should be already normalized
+pub async fn set_post_tags(post_id: &String, names: &[String], tx: &mut Transaction<'_>) {
+    tx.execute(
+        "
+        DELETE FROM post_tags pt
+        USING tags t
+        WHERE pt.post_id = $1 AND t.tag_id = pt.tag_id
+          AND NOT (t.name = ANY($2))
+        ",
+        &[post_id, &names],
+    )
+    .await
+    .unwrap();
+    if names.is_empty() {
+        return;
+    }
+
+    let ids: Vec<String> = names.iter().map(|_| generate_id().to_string()).collect();
+    tx.execute(
+        "
+        INSERT INTO tags (tag_id, name)
+        SELECT * FROM unnest($1::TEXT[], $2::TEXT[])
+        ON CONFLICT (name) DO NOTHING
+        ",
+        &[&ids, &names],
+    )
+    .await
+    .unwrap();
+    tx.execute(
+        "
+        INSERT INTO post_tags (post_id, tag_id)
+        SELECT $1, tag_id FROM tags
+        WHERE name = ANY($2)
+        ON CONFLICT (post_id, tag_id) DO NOTHING
+        ",
+        &[post_id, &names],
+    )
+    .await
+    .unwrap();
+}
+
+/// Saves current version of post as revision, called before it's changed in the same transaction
+/// Post row is locked until commit, so concurrent edits can't save the same version twice
+pub async fn create_post_revision(post_id: &String, tx: &mut Transaction<'_>) {
+    tx.execute(
+        "SELECT 1 FROM posts WHERE post_id = $1 FOR UPDATE",
+        &[post_id],
+    )
+    .await
+    .unwrap();
+    tx.execute(
+        "
+        INSERT INTO post_revisions (
+            revision_id, post_id, content, tags, visibility, file_context_id, created_at
+        )
+        SELECT $2, p.post_id, p.content,
+               ARRAY(
+                   SELECT t.name FROM post_tags pt
+                   JOIN tags t ON t.tag_id = pt.tag_id
+                   WHERE pt.post_id = p.post_id
+                   ORDER BY t.name
+               ),
+               p.visibility, p.file_context_id, COALESCE(p.updated_at, p.created_at)
+        FROM posts p
+        WHERE p.post_id = $1
+        ",
+        &[post_id, &generate_id().to_string()],
+    )
+    .await
+    .unwrap();
+    // Tag changes don't touch content, so updated_at is bumped here for the new version
+    tx.execute(
+        "
+        UPDATE posts
+        SET revisions_count = revisions_count + 1, updated_at = NOW()
+        WHERE post_id = $1
+        ",
+        &[post_id],
+    )
+    .await
+    .unwrap();
+}
+
+/// Get page of post revisions, newest first
+pub async fn get_post_revisions(
+    post_id: &String,
+    cursor: &CursorParams,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<PostRevision> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "
+        SELECT r.revision_id, r.content, r.tags, r.visibility,
+               COALESCE(m.objects, '{{}}') AS media, m.type AS media_type,
+               EXTRACT(EPOCH FROM r.created_at)::BIGINT AS created_at
+        FROM post_revisions r
+        LEFT JOIN files m ON m.context_id = r.file_context_id
+        WHERE r.post_id = $1 AND {} {}
+        ",
+        cursor.where_clause("r.revision_id", 2),
+        cursor.order_clause("r.revision_id", 4),
+    );
+
+    let rows = db
+        .query(
+            &sql,
+            &[post_id, &cursor.before, &cursor.after, &cursor.limit()],
+        )
+        .await
+        .unwrap();
+    let revisions = rows
+        .into_iter()
+        .map(|r| PostRevision {
+            revision_id: r.get("revision_id"),
+            visibility: r.get("visibility"),
+            content: r.get("content"),
+            tags: r.get("tags"),
+            media: build_links(r.get("media"), state),
+            media_type: r.get("media_type"),
+            created_at: r.get("created_at"),
+        })
+        .collect();
+    cursor.arrange(revisions)
+}
+
+/// Turns post into draft, or into scheduled post if 'publish_at' is set
+pub async fn set_post_schedule(
+    post_id: &String,
+    publish_at: Option<i64>,
+    tx: &mut Transaction<'_>,
+) {
+    tx.execute(
+        "
+        UPDATE posts
+        SET status = CASE WHEN $2::BIGINT IS NULL THEN 'draft' ELSE 'scheduled' END,
+            publish_at = to_timestamp($2::BIGINT)
+        WHERE post_id = $1
+        ",
+        &[post_id, &publish_at],
+    )
+    .await
+    .unwrap();
+}
+
+/// Get page of user's drafts and scheduled posts, newest first
+pub async fn get_drafts(
+    user_id: &str,
+    cursor: &CursorParams,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<Post> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {} FROM {}
+        WHERE p.user_id = $1 AND p.status IN ('draft', 'scheduled')
+        AND NOT COALESCE(p.is_deleted, FALSE) AND {} {} {}",
+        POST_COLUMNS,
+        POST_FROM,
+        cursor.where_clause("p.post_id", 2),
+        POST_GROUP,
+        cursor.order_clause("p.post_id", 4),
+    );
+
+    let rows = db
+        .query(
+            &sql,
+            &[&user_id, &cursor.before, &cursor.after, &cursor.limit()],
+        )
+        .await
+        .unwrap();
+    let posts = rows.into_iter().map(|r| row_to_post(r, state)).collect();
+    cursor.arrange(posts)
+}
+
+/// Get user's draft or scheduled post
+pub async fn get_draft(
+    post_id: &str,
+    user_id: &str,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Option<Post> {
+    let db = conn.get_client().await.unwrap();
+    let sql = format!(
+        "SELECT {} FROM {}
+        WHERE p.user_id = $1 AND p.post_id = $2 AND p.status IN ('draft', 'scheduled')
+        AND NOT COALESCE(p.is_deleted, FALSE) {}",
+        POST_COLUMNS, POST_FROM, POST_GROUP
+    );
+
+    let row = db.query_opt(&sql, &[&user_id, &post_id]).await.unwrap();
     row.map(|r| row_to_post(r, state))
 }
+
+/// Get up to 'limit' scheduled posts that are due, rows stay locked until transaction ends
+/// and rows locked by other servers are skipped, so every post is published once
+/// Returns: (post_id, author_id, content)
+pub async fn lock_due_posts(limit: i64, tx: &mut Transaction<'_>) -> Vec<(String, String, String)> {
+    let rows = tx
+        .query(
+            "
+            SELECT post_id, user_id, content FROM posts
+            WHERE status = 'scheduled' AND publish_at <= now()
+              AND NOT COALESCE(is_deleted, FALSE)
+            ORDER BY publish_at
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            ",
+            &[&limit],
+        )
+        .await
+        .unwrap();
+    rows.into_iter()
+        .map(|r| (r.get("post_id"), r.get("user_id"), r.get("content")))
+        .collect()
+}
+
+/// Publishes draft or scheduled post, it keeps its id but is placed in feeds by publish time
+/// Polls run for the same duration from publish time and are reopened if closed meanwhile
+/// Returns false if post isn't a draft or scheduled
+pub async fn publish_post(post_id: &String, tx: &mut Transaction<'_>) -> bool {
+    let published_id = generate_id() as i64;
+    tx.execute(
+        "
+        UPDATE polls pl
+        SET expires_at = pl.expires_at + (now() - p.created_at), closed_at = NULL
+        FROM posts p
+        WHERE p.post_id = pl.post_id AND p.post_id = $1
+          AND p.status IN ('draft', 'scheduled')
+        ",
+        &[post_id],
+    )
+    .await
+    .unwrap();
+    let affected = tx
+        .execute(
+            "
+            UPDATE posts
+            SET published_id = $2, status = 'active', publish_at = NULL,
+                created_at = now(), updated_at = now()
+            WHERE post_id = $1 AND status IN ('draft', 'scheduled')
+            ",
+            &[post_id, &published_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Soft deletes or restores post, deleted_at is maintained by trigger
+/// Returns false if post was already in that state
+pub async fn set_post_deleted(post_id: &String, deleted: bool, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            UPDATE posts
+            SET is_deleted = $2
+            WHERE post_id = $1 AND COALESCE(is_deleted, FALSE) <> $2
+            ",
+            &[post_id, &deleted],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Hard deletes up to 'limit' posts soft deleted more than 'retention_days' ago
+/// Reposts of them go too, children are removed by cascades and delete triggers
+/// Posts referenced by moderation audit are kept as tombstones
+/// Returns amount of purged posts, reposts aren't counted
+pub async fn purge_deleted_posts(retention_days: i64, limit: i64, tx: &mut Transaction<'_>) -> u64 {
+    let rows = tx
+        .query(
+            "
+            WITH due AS (
+                SELECT post_id FROM posts
+                WHERE is_deleted AND deleted_at <= now() - $1::BIGINT * INTERVAL '1 day'
+                ORDER BY deleted_at
+                LIMIT $2
+                FOR UPDATE SKIP LOCKED
+            ),
+            tombstones AS (
+                INSERT INTO post_tombstones (
+                    post_id, user_id, content, tags, file_context_id, created_at, deleted_at
+                )
+                SELECT p.post_id, p.user_id, p.content,
+                       ARRAY(
+                           SELECT t.name FROM post_tags pt
+                           JOIN tags t ON t.tag_id = pt.tag_id
+                           WHERE pt.post_id = p.post_id
+                           ORDER BY t.name
+                       ),
+                       p.file_context_id, p.created_at, p.deleted_at
+                FROM posts p
+                JOIN due d ON d.post_id = p.post_id
+                WHERE EXISTS (
+                    SELECT 1 FROM mod_audit a
+                    WHERE a.target_type = 'post' AND a.target_id = p.post_id
+                )
+                ON CONFLICT (post_id) DO NOTHING
+            ),
+            purged AS (
+                DELETE FROM posts
+                WHERE post_id IN (SELECT post_id FROM due)
+                   OR (post_type = 'repost' AND reference_post_id IN (SELECT post_id FROM due))
+                RETURNING post_id
+            )
+            SELECT COUNT(*) AS purged FROM purged
+            WHERE post_id IN (SELECT post_id FROM due)
+            ",
+            &[&retention_days, &limit],
+        )
+        .await
+        .unwrap();
+    rows[0].get::<_, i64>("purged") as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::{
+            audit::{AuditEntry, create_audit_entry},
+            blocks::{block_user, mute_user},
+            favorites::add_favorite,
+            follows::follow_user,
+            notifications::create_notifications,
+            reactions::set_reaction,
+            settings::update_user_settings,
+            tags::get_tag,
+            test_support::{cleanup, ids, new_post, new_user},
+            views::{PostView, insert_views},
+        },
+        entities::settings::UserSettings,
+        get_conn,
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn get_post_returns_full_entity() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let post_id = new_post(&author, &["rust", "go"], &mut conn).await;
+
+        let post = get_post(&post_id, &author, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!(post.user_id, author);
+        assert_eq!(post.flags, vec!["spoiler"]);
+        assert_eq!(post.status.as_deref(), Some("active"));
+        let mut tags = post.tags.unwrap();
+        tags.sort();
+        assert_eq!(tags, vec!["go", "rust"]);
+
+        cleanup(&[&author], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn get_post_skips_deleted() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let post_id = new_post(&author, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        assert!(set_post_deleted(&post_id, true, &mut tx).await);
+        tx.commit().await.unwrap();
+
+        assert!(
+            get_post(&post_id, &author, &mut conn, &state, false)
+                .await
+                .is_none()
+        );
+        let post = get_post(&post_id, &author, &mut conn, &state, true)
+            .await
+            .unwrap();
+        assert_eq!(post.is_deleted, Some(true));
+
+        cleanup(&[&author], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn private_posts_are_visible_to_followers_only() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let follower = new_user(&mut conn).await;
+        let stranger = new_user(&mut conn).await;
+        let post_id = new_post(&author, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        tx.execute(
+            "INSERT INTO user_profiles (user_id, is_private) VALUES ($1, TRUE)",
+            &[&author],
+        )
+        .await
+        .unwrap();
+        follow_user(&follower, &author, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        for (viewer, visible) in [(&author, true), (&follower, true), (&stranger, false)] {
+            let post = get_post(&post_id, viewer, &mut conn, &state, false).await;
+            assert_eq!(post.is_some(), visible);
+            let posts =
+                get_user_posts(&author, viewer, &CursorParams::default(), &mut conn, &state).await;
+            assert_eq!(posts.len(), visible as usize);
+        }
+
+        cleanup(&[&author, &follower, &stranger], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn user_posts_cursor_pagination() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let mut created = Vec::new();
+        for _ in 0..5 {
+            created.push(new_post(&author, &[], &mut conn).await);
+        }
+        created.reverse();
+        let newest: Vec<&str> = created.iter().map(|s| s.as_str()).collect();
+
+        let page = |before: Option<&str>, after: Option<&str>| CursorParams {
+            before: before.map(|id| id.parse().unwrap()),
+            after: after.map(|id| id.parse().unwrap()),
+            limit: Some(2),
+        };
+
+        let first = get_user_posts(&author, &author, &page(None, None), &mut conn, &state).await;
+        assert_eq!(ids(&first), newest[0..2]);
+
+        let second = get_user_posts(
+            &author,
+            &author,
+            &page(Some(newest[1]), None),
+            &mut conn,
+            &state,
+        )
+        .await;
+        assert_eq!(ids(&second), newest[2..4]);
+
+        // Newer items directly after cursor, still newest first
+        let newer = get_user_posts(
+            &author,
+            &author,
+            &page(None, Some(newest[4])),
+            &mut conn,
+            &state,
+        )
+        .await;
+        assert_eq!(ids(&newer), newest[2..4]);
+
+        cleanup(&[&author], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn home_feed_filters_blocked_muted_and_deleted() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let viewer = new_user(&mut conn).await;
+        let friend = new_user(&mut conn).await;
+        let muted = new_user(&mut conn).await;
+        let blocker = new_user(&mut conn).await;
+        let stranger = new_user(&mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        for user_id in [&friend, &muted, &blocker] {
+            follow_user(&viewer, user_id, &mut tx).await;
+        }
+        mute_user(&viewer, &muted, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        let own = new_post(&viewer, &[], &mut conn).await;
+        let friend_post = new_post(&friend, &[], &mut conn).await;
+        let deleted = new_post(&friend, &[], &mut conn).await;
+        new_post(&muted, &[], &mut conn).await;
+        new_post(&blocker, &[], &mut conn).await;
+        new_post(&stranger, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        set_post_deleted(&deleted, true, &mut tx).await;
+        block_user(&blocker, &viewer, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        // Without timeline everything comes from followed accounts directly
+        let source = HomeSource {
+            exhausted: true,
+            ..Default::default()
+        };
+        let posts = get_home_posts(&viewer, &source, None, 20, &mut conn, &state).await;
+        assert_eq!(ids(&posts), vec![friend_post.as_str(), own.as_str()]);
+
+        cleanup(&[&viewer, &friend, &muted, &blocker, &stranger], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn home_feed_applies_content_filters() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let viewer = new_user(&mut conn).await;
+        let author = new_user(&mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        follow_user(&viewer, &author, &mut tx).await;
+        let mut create = async |user_id: &String, content: &str, flags: &[&str]| {
+            let flags = flags.iter().map(|f| f.to_string()).collect();
+            create_post(
+                user_id,
+                &content.to_string(),
+                &None,
+                &flags,
+                &None,
+                "public",
+                &mut tx,
+            )
+            .await
+        };
+        let plain = create(&author, "hello", &[]).await;
+        let sensitive = create(&author, "hello", &["sensitive"]).await;
+        let spoiled = create(&author, "The Ending is sad", &["spoiler"]).await;
+        let own = create(&viewer, "my ending", &["sensitive"]).await;
+        tx.commit().await.unwrap();
+
+        let source = HomeSource {
+            exhausted: true,
+            ..Default::default()
+        };
+        // Sensitive posts are hidden by default
+        let posts = get_home_posts(&viewer, &source, None, 20, &mut conn, &state).await;
+        assert_eq!(
+            ids(&posts),
+            vec![own.as_str(), spoiled.as_str(), plain.as_str()]
+        );
+
+        let mut settings = UserSettings::default();
+        settings.content_filters.hide_sensitive = false;
+        settings.content_filters.muted_words = vec!["ending".to_string()];
+        let mut tx = create_tx!(conn);
+        update_user_settings(&viewer, &settings, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        // Own posts are never filtered
+        let posts = get_home_posts(&viewer, &source, None, 20, &mut conn, &state).await;
+        assert_eq!(
+            ids(&posts),
+            vec![own.as_str(), sensitive.as_str(), plain.as_str()]
+        );
+
+        cleanup(&[&viewer, &author], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn home_feed_merges_large_accounts_within_timeline_page() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let viewer = new_user(&mut conn).await;
+        let small = new_user(&mut conn).await;
+        let large = new_user(&mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        follow_user(&viewer, &small, &mut tx).await;
+        follow_user(&viewer, &large, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        let large_old = new_post(&large, &[], &mut conn).await;
+        let small_old = new_post(&small, &[], &mut conn).await;
+        let large_mid = new_post(&large, &[], &mut conn).await;
+        let small_new = new_post(&small, &[], &mut conn).await;
+
+        // Timeline page ends at small_new, older large posts wait for the next page
+        let source = HomeSource {
+            post_ids: vec![small_new.clone()],
+            large_ids: vec![large.clone()],
+            oldest: small_new.parse().ok(),
+            exhausted: false,
+        };
+        let posts = get_home_posts(&viewer, &source, None, 20, &mut conn, &state).await;
+        assert_eq!(ids(&posts), vec![small_new.as_str()]);
+
+        let source = HomeSource {
+            post_ids: vec![small_old.clone()],
+            large_ids: vec![large.clone()],
+            oldest: small_old.parse().ok(),
+            exhausted: true,
+        };
+        let before = small_new.parse().ok();
+        let posts = get_home_posts(&viewer, &source, before, 20, &mut conn, &state).await;
+        assert_eq!(
+            ids(&posts),
+            vec![large_mid.as_str(), small_old.as_str(), large_old.as_str()]
+        );
+
+        cleanup(&[&viewer, &small, &large], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn popular_ranking_decays_with_age() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let fan = new_user(&mut conn).await;
+        let fresh = new_post(&author, &[], &mut conn).await;
+        let liked = new_post(&author, &[], &mut conn).await;
+        let stale = new_post(&author, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        set_reaction(&fan, &liked, None, Reaction::Like, &mut tx).await;
+        set_reaction(&fan, &stale, None, Reaction::Like, &mut tx).await;
+        tx.execute(
+            "UPDATE posts SET created_at = now() - INTERVAL '30 hours' WHERE post_id = $1",
+            &[&liked],
+        )
+        .await
+        .unwrap();
+        tx.execute(
+            "UPDATE posts SET created_at = now() - INTERVAL '100 hours' WHERE post_id = $1",
+            &[&stale],
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        let ranking = get_popular_ranking(1.8, 48, 1000, &mut conn).await;
+        let ranked: Vec<&str> = ranking
+            .iter()
+            .map(|(id, _, _)| id.as_str())
+            .filter(|id| [&fresh, &liked, &stale].iter().any(|p| p == id))
+            .collect();
+        assert_eq!(ranked, vec![fresh.as_str(), liked.as_str()]);
+
+        // Posts keep the requested order
+        let posts =
+            get_posts_by_ids(&fan, &[liked.clone(), fresh.clone()], &mut conn, &state).await;
+        assert_eq!(ids(&posts), vec![liked.as_str(), fresh.as_str()]);
+
+        cleanup(&[&author, &fan], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn recommendations_skip_seen_posts() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let viewer = new_user(&mut conn).await;
+        let followed = new_user(&mut conn).await;
+        let stranger = new_user(&mut conn).await;
+        let tag = format!("t{}", generate_id());
+
+        let liked = new_post(&stranger, &[&tag], &mut conn).await;
+        let same_tag = new_post(&stranger, &[&tag], &mut conn).await;
+        let seen = new_post(&followed, &[], &mut conn).await;
+        let unseen = new_post(&followed, &[], &mut conn).await;
+        new_post(&stranger, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        follow_user(&viewer, &followed, &mut tx).await;
+        set_reaction(&viewer, &liked, None, Reaction::Like, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        let views: Vec<PostView> = [&seen, &liked]
+            .into_iter()
+            .map(|post_id| PostView {
+                user_id: viewer.clone(),
+                post_id: post_id.clone(),
+                timestamp: 1,
+            })
+            .collect();
+        insert_views(&views, &mut conn).await;
+
+        let ranking = get_recommended_ranking(&viewer, &[], 1.8, 48, 500, &mut conn).await;
+        let mut ranked: Vec<&str> = ranking.iter().map(|(id, _)| id.as_str()).collect();
+        ranked.sort();
+        let mut expected = vec![same_tag.as_str(), unseen.as_str()];
+        expected.sort();
+        assert_eq!(ranked, expected);
+
+        cleanup(&[&viewer, &followed, &stranger], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn search_matches_stems_prefixes_and_phrases() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let viewer = new_user(&mut conn).await;
+        let marker = format!("m{}", generate_id());
+
+        let db = conn.get_client().await.unwrap();
+        db.execute(
+            "
+            INSERT INTO user_profiles (user_id, languages) VALUES ($1, '{en}')
+            ON CONFLICT (user_id) DO UPDATE SET languages = EXCLUDED.languages
+            ",
+            &[&author],
+        )
+        .await
+        .unwrap();
+        let mut posts = Vec::new();
+        for content in [
+            "The runners were running",
+            "Quick brown fox",
+            "Brown quick fox",
+            "Quick deleted fox",
+        ] {
+            let mut tx = create_tx!(conn);
+            let content = format!("{} {}", content, marker);
+            posts.push(
+                create_post(&author, &content, &None, &vec![], &None, "public", &mut tx).await,
+            );
+            tx.commit().await.unwrap();
+        }
+        let mut tx = create_tx!(conn);
+        set_post_deleted(&posts[3], true, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        let search = |text: &str, prefixes: &[&str]| PostSearch {
+            text: format!("{} {}", text, marker),
+            prefixes: prefixes.iter().map(|p| p.to_string()).collect(),
+            language: Some("en".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        let found = search_posts(&viewer, &search("run", &[]), &mut conn, &state).await;
+        assert_eq!(ids(&found), vec![posts[0].as_str()]);
+
+        let found = search_posts(&viewer, &search("\"brown fox\"", &[]), &mut conn, &state).await;
+        assert_eq!(ids(&found), vec![posts[1].as_str()]);
+
+        let mut found: Vec<String> =
+            search_posts(&viewer, &search("", &["qui"]), &mut conn, &state)
+                .await
+                .into_iter()
+                .map(|p| p.post_id)
+                .collect();
+        found.sort();
+        assert_eq!(found, vec![posts[1].clone(), posts[2].clone()]);
+
+        let mut other_author = search("fox", &[]);
+        other_author.author_id = Some(viewer.clone());
+        assert!(
+            search_posts(&viewer, &other_author, &mut conn, &state)
+                .await
+                .is_empty()
+        );
+
+        cleanup(&[&author, &viewer], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn edits_keep_revisions() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let post_id = new_post(&author, &["rust"], &mut conn).await;
+
+        let post = get_post(&post_id, &author, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert!(!post.edited);
+
+        for (content, tags) in [("second", vec!["rust"]), ("second", vec!["go"])] {
+            let tags: Vec<String> = tags.into_iter().map(String::from).collect();
+            let mut tx = create_tx!(conn);
+            create_post_revision(&post_id, &mut tx).await;
+            update_post(&post_id, &Some(content.to_string()), &None, &None, &mut tx).await;
+            set_post_tags(&post_id, &tags, &mut tx).await;
+            tx.commit().await.unwrap();
+        }
+
+        let post = get_post(&post_id, &author, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert!(post.edited);
+        assert_eq!(post.revisions_count, 2);
+
+        let revisions =
+            get_post_revisions(&post_id, &CursorParams::default(), &mut conn, &state).await;
+        let versions: Vec<(&str, &Vec<String>)> = revisions
+            .iter()
+            .map(|r| (r.content.as_str(), &r.tags))
+            .collect();
+        assert_eq!(
+            versions,
+            vec![
+                ("second", &vec!["rust".to_string()]),
+                ("content", &vec!["rust".to_string()]),
+            ]
+        );
+        assert!(revisions[0].created_at >= revisions[1].created_at);
+
+        cleanup(&[&author], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn reposts_embed_original() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let reposter = new_user(&mut conn).await;
+        let original = new_post(&author, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        let repost = create_repost(&reposter, &original, &mut tx).await.unwrap();
+        assert!(create_repost(&reposter, &original, &mut tx).await.is_none());
+        let quote = create_post(
+            &reposter,
+            &"quote".to_string(),
+            &None,
+            &vec![],
+            &Some(original.clone()),
+            "public",
+            &mut tx,
+        )
+        .await;
+        tx.commit().await.unwrap();
+
+        let post = get_post(&original, &reposter, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!((post.reposts_count, post.quotes_count), (1, 1));
+        assert!(post.is_reposted);
+
+        // Reposts are attributed to reposter and carry the original
+        let posts = get_user_posts(
+            &reposter,
+            &reposter,
+            &CursorParams::default(),
+            &mut conn,
+            &state,
+        )
+        .await;
+        assert_eq!(ids(&posts), vec![quote.as_str(), repost.as_str()]);
+        assert!(
+            posts
+                .iter()
+                .all(|p| p.reference.as_ref().map(|r| &r.post_id) == Some(&original))
+        );
+
+        // Deleted original degrades to a bare reference
+        let mut tx = create_tx!(conn);
+        set_post_deleted(&original, true, &mut tx).await;
+        tx.commit().await.unwrap();
+        let post = get_post(&quote, &reposter, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!(post.reference_post_id, Some(original.clone()));
+        assert!(post.reference.is_none());
+
+        let mut tx = create_tx!(conn);
+        set_post_deleted(&original, false, &mut tx).await;
+        assert!(delete_repost(&reposter, &repost, &mut tx).await);
+        set_post_deleted(&quote, true, &mut tx).await;
+        tx.commit().await.unwrap();
+        let post = get_post(&original, &reposter, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!((post.reposts_count, post.quotes_count), (0, 0));
+        assert!(!post.is_reposted);
+
+        cleanup(&[&author, &reposter], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn scheduled_posts_publish_once() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let draft = new_post(&author, &["rust"], &mut conn).await;
+        let scheduled = new_post(&author, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        set_post_schedule(&draft, None, &mut tx).await;
+        set_post_schedule(&scheduled, Some(Utc::now().timestamp() - 1), &mut tx).await;
+        tx.commit().await.unwrap();
+
+        // Unpublished posts are only listed to author as drafts
+        assert!(
+            get_post(&draft, &author, &mut conn, &state, false)
+                .await
+                .is_none()
+        );
+        let posts = get_user_posts(
+            &author,
+            &author,
+            &CursorParams::default(),
+            &mut conn,
+            &state,
+        )
+        .await;
+        assert!(posts.is_empty());
+        let drafts = get_drafts(&author, &CursorParams::default(), &mut conn, &state).await;
+        assert_eq!(ids(&drafts), vec![scheduled.as_str(), draft.as_str()]);
+        assert_eq!(drafts[0].status.as_deref(), Some("scheduled"));
+
+        crate::services::publisher::publish_due(state.clone()).await;
+        let posts = get_user_posts(
+            &author,
+            &author,
+            &CursorParams::default(),
+            &mut conn,
+            &state,
+        )
+        .await;
+        assert_eq!(ids(&posts), vec![scheduled.as_str()]);
+
+        // Already published post can't be published again, draft keeps its id
+        let mut tx = create_tx!(conn);
+        assert!(!publish_post(&scheduled, &mut tx).await);
+        assert!(publish_post(&draft, &mut tx).await);
+        assert!(!publish_post(&draft, &mut tx).await);
+        tx.commit().await.unwrap();
+
+        let post = get_post(&draft, &author, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!(post.tags, Some(vec!["rust".to_string()]));
+
+        // Feeds are ordered by publish time, older draft published last comes first
+        let posts = get_user_posts(
+            &author,
+            &author,
+            &CursorParams::default(),
+            &mut conn,
+            &state,
+        )
+        .await;
+        assert_eq!(ids(&posts), vec![draft.as_str(), scheduled.as_str()]);
+        let cursor = CursorParams {
+            before: Some(draft.parse().unwrap()),
+            ..Default::default()
+        };
+        let posts = get_user_posts(&author, &author, &cursor, &mut conn, &state).await;
+        assert_eq!(ids(&posts), vec![scheduled.as_str()]);
+        assert!(
+            get_drafts(&author, &CursorParams::default(), &mut conn, &state)
+                .await
+                .is_empty()
+        );
+
+        cleanup(&[&author], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn visibility_levels_are_enforced() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let follower = new_user(&mut conn).await;
+        let friend = new_user(&mut conn).await;
+        let stranger = new_user(&mut conn).await;
+        let tag = format!("t{}", generate_id());
+
+        let mut tx = create_tx!(conn);
+        follow_user(&follower, &author, &mut tx).await;
+        tx.execute(
+            "INSERT INTO friends (user_id, friend_id) VALUES ($1, $2)",
+            &[&author, &friend],
+        )
+        .await
+        .unwrap();
+        let mut posts = Vec::new();
+        for visibility in ["followers", "friends", "private", "unlisted"] {
+            let post_id = create_post(
+                &author,
+                &"content".to_string(),
+                &None,
+                &vec![],
+                &None,
+                visibility,
+                &mut tx,
+            )
+            .await;
+            set_post_tags(&post_id, std::slice::from_ref(&tag), &mut tx).await;
+            posts.push(post_id);
+        }
+        tx.commit().await.unwrap();
+
+        let mut visible = Vec::new();
+        for viewer in [&author, &follower, &friend, &stranger] {
+            let mut seen = Vec::new();
+            for post_id in &posts {
+                seen.push(
+                    get_post(post_id, viewer, &mut conn, &state, false)
+                        .await
+                        .is_some(),
+                );
+            }
+            visible.push(seen);
+        }
+        assert_eq!(
+            visible,
+            vec![
+                vec![true, true, true, true],
+                vec![true, false, false, true],
+                vec![false, true, false, true],
+                vec![false, false, false, true],
+            ]
+        );
+
+        // Unlisted posts are reachable by link only
+        let found = get_tag(&tag, &mut conn).await.unwrap();
+        let cursor = CursorParams::default();
+        let listed = get_tag_posts(&found.tag_id, &stranger, &cursor, &mut conn, &state).await;
+        assert!(listed.is_empty());
+        let listed = get_tag_posts(&found.tag_id, &author, &cursor, &mut conn, &state).await;
+        assert_eq!(listed.len(), 4);
+
+        // Revision keeps the audience post had before the change
+        let mut tx = create_tx!(conn);
+        create_post_revision(&posts[2], &mut tx).await;
+        assert!(
+            update_post(
+                &posts[2],
+                &None,
+                &None,
+                &Some("public".to_string()),
+                &mut tx
+            )
+            .await
+        );
+        tx.commit().await.unwrap();
+        assert!(
+            get_post(&posts[2], &stranger, &mut conn, &state, false)
+                .await
+                .is_some()
+        );
+        let revisions = get_post_revisions(&posts[2], &cursor, &mut conn, &state).await;
+        assert_eq!(revisions.len(), 1);
+        assert_eq!(revisions[0].visibility, "private");
+
+        cleanup(&[&author, &follower, &friend, &stranger], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn purge_keeps_tombstones_of_audited_posts() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let reposter = new_user(&mut conn).await;
+        let audited = new_post(&author, &["rust"], &mut conn).await;
+        let expired = new_post(&author, &[], &mut conn).await;
+        let recent = new_post(&author, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        let repost = create_repost(&reposter, &expired, &mut tx).await.unwrap();
+        create_notifications(
+            std::slice::from_ref(&author),
+            &reposter,
+            "repost",
+            Some(("post", &expired)),
+            &mut tx,
+        )
+        .await;
+        create_audit_entry(
+            AuditEntry {
+                user_id: &reposter,
+                towards_to: &author,
+                role_id: 0,
+                target_type: "post",
+                target_id: &audited,
+                action_type: "delete_post",
+                reason: "test",
+                metadata: None,
+                old_content: None,
+            },
+            &mut tx,
+        )
+        .await;
+        for post_id in [&audited, &expired, &recent] {
+            set_post_deleted(post_id, true, &mut tx).await;
+        }
+        tx.execute(
+            "
+            UPDATE posts SET deleted_at = now() - INTERVAL '100 days'
+            WHERE post_id = ANY($1)
+            ",
+            &[&vec![audited.clone(), expired.clone()]],
+        )
+        .await
+        .unwrap();
+        tx.commit().await.unwrap();
+
+        crate::services::purge::purge(state.clone()).await;
+
+        let db = conn.get_client().await.unwrap();
+        let left: Vec<String> = db
+            .query(
+                "SELECT post_id FROM posts WHERE post_id = ANY($1) ORDER BY post_id",
+                &[&vec![
+                    audited.clone(),
+                    expired.clone(),
+                    recent.clone(),
+                    repost,
+                ]],
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.get("post_id"))
+            .collect();
+        assert_eq!(left, vec![recent.clone()]);
+        let notifications: i64 = db
+            .query_one(
+                "
+                SELECT COUNT(*) FROM user_notifications
+                WHERE linked_type = 'post' AND linked_id = $1
+                ",
+                &[&expired],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(notifications, 0);
+        let tombstones: Vec<(String, Vec<String>)> = db
+            .query(
+                "SELECT post_id, tags FROM post_tombstones WHERE post_id = ANY($1)",
+                &[&vec![audited.clone(), expired.clone()]],
+            )
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| (r.get("post_id"), r.get("tags")))
+            .collect();
+        assert_eq!(
+            tombstones,
+            vec![(audited.clone(), vec!["rust".to_string()])]
+        );
+
+        db.execute(
+            "DELETE FROM post_tombstones WHERE post_id = $1",
+            &[&audited],
+        )
+        .await
+        .unwrap();
+        db.execute("DELETE FROM mod_audit WHERE target_id = $1", &[&audited])
+            .await
+            .unwrap();
+        cleanup(&[&author, &reposter], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn published_post_keeps_its_children() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let viewer = new_user(&mut conn).await;
+        let draft = new_post(&author, &[], &mut conn).await;
+        let mut tx = create_tx!(conn);
+        set_post_schedule(&draft, None, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        // Views of unpublished posts aren't recorded
+        let view = PostView {
+            user_id: viewer.clone(),
+            post_id: draft.clone(),
+            timestamp: Utc::now().timestamp(),
+        };
+        assert_eq!(insert_views(&[view], &mut conn).await, 0);
+
+        // Rows that reached the draft anyway stay with it after publishing
+        let db = conn.get_client().await.unwrap();
+        db.execute(
+            "INSERT INTO user_post_views (user_id, post_id) VALUES ($1, $2)",
+            &[&viewer, &draft],
+        )
+        .await
+        .unwrap();
+        let mut tx = create_tx!(conn);
+        add_favorite(&author, &draft, None, &mut tx).await;
+        assert!(publish_post(&draft, &mut tx).await);
+        tx.commit().await.unwrap();
+
+        let db = conn.get_client().await.unwrap();
+        let kept: i64 = db
+            .query_one(
+                "
+                SELECT (SELECT COUNT(*) FROM user_post_views WHERE post_id = $1)
+                     + (SELECT COUNT(*) FROM favorites WHERE post_id = $1)
+                ",
+                &[&draft],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(kept, 2);
+
+        cleanup(&[&author, &viewer], &mut conn).await;
+    }
+}
@@ -0,0 +1,73 @@
+use chrono::Utc;
+use fred::prelude::{Expiration, KeysInterface};
+use serde::Serialize;
+
+use crate::utils::state::ArcAppState;
+
+/// User is online while heartbeat key lives
+const ONLINE_TTL: i64 = 60;
+const LAST_SEEN_TTL: i64 = 30 * 24 * 3600;
+
+#[derive(Debug, Default, Serialize)]
+pub struct Presence {
+    pub online: bool,
+    pub last_seen_at: Option<i64>,
+}
+
+/// Marks user as online, called on authenticated requests and heartbeats
+pub async fn touch_presence(user_id: &str, state: &ArcAppState) {
+    let now = Utc::now().timestamp();
+
+    // Presence is best effort, so errors are ignored
+    let _: Result<(), _> = state
+        .sessions_redis
+        .set(
+            format!("online:{}", user_id),
+            1,
+            Some(Expiration::EX(ONLINE_TTL)),
+            None,
+            false,
+        )
+        .await;
+    let _: Result<(), _> = state
+        .sessions_redis
+        .set(
+            format!("last_seen:{}", user_id),
+            now,
+            Some(Expiration::EX(LAST_SEEN_TTL)),
+            None,
+            false,
+        )
+        .await;
+}
+
+/// Get presence of users in the same order as user_ids
+pub async fn get_presences(user_ids: &[String], state: &ArcAppState) -> Vec<Presence> {
+    if user_ids.is_empty() {
+        return Vec::new();
+    }
+
+    let online_keys: Vec<String> = user_ids.iter().map(|id| format!("online:{}", id)).collect();
+    let seen_keys: Vec<String> = user_ids
+        .iter()
+        .map(|id| format!("last_seen:{}", id))
+        .collect();
+
+    let online: Vec<Option<i64>> = state
+        .sessions_redis
+        .mget(online_keys)
+        .await
+        .unwrap_or_default();
+    let last_seen: Vec<Option<i64>> = state
+        .sessions_redis
+        .mget(seen_keys)
+        .await
+        .unwrap_or_default();
+
+    (0..user_ids.len())
+        .map(|i| Presence {
+            online: online.get(i).copied().flatten().is_some(),
+            last_seen_at: last_seen.get(i).copied().flatten(),
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+use deadpool_postgres::Transaction;
+
+use crate::entities::reaction::Reaction;
+
+/// Sets reaction of user on post, or on comment if comment_id is given
+/// Existing reaction is replaced, counters are updated by triggers
+pub async fn set_reaction(
+    user_id: &String,
+    post_id: &String,
+    comment_id: Option<&String>,
+    reaction: Reaction,
+    tx: &mut Transaction<'_>,
+) {
+    // Conflict target has to match one of the partial unique indexes
+    let conflict = match comment_id {
+        None => "(post_id, user_id) WHERE comment_id IS NULL",
+        Some(_) => "(comment_id, user_id) WHERE comment_id IS NOT NULL",
+    };
+    tx.execute(
+        &format!(
+            "
+            INSERT INTO reactions (user_id, post_id, comment_id, is_like)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT {}
+            DO UPDATE SET is_like = EXCLUDED.is_like
+            ",
+            conflict
+        ),
+        &[user_id, post_id, &comment_id, &reaction.is_like()],
+    )
+    .await
+    .unwrap();
+}
+
+/// Removes reaction of user, returns false if there was none
+pub async fn remove_reaction(
+    user_id: &String,
+    post_id: &String,
+    comment_id: Option<&String>,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let affected = tx
+        .execute(
+            "
+            DELETE FROM reactions
+            WHERE user_id = $1 AND post_id = $2
+              AND comment_id IS NOT DISTINCT FROM $3
+            ",
+            &[user_id, post_id, &comment_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::{
+            conn::LazyConn,
+            posts::get_post,
+            test_support::{cleanup, new_post, new_user},
+        },
+        get_conn,
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn reactions_are_unique_per_user() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let viewer = new_user(&mut conn).await;
+        let post_id = new_post(&author, &[], &mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        set_reaction(&viewer, &post_id, None, Reaction::Like, &mut tx).await;
+        set_reaction(&viewer, &post_id, None, Reaction::Dislike, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        let post = get_post(&post_id, &viewer, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!((post.likes_count, post.dislikes_count), (0, 1));
+        assert_eq!(post.reaction, Some(Reaction::Dislike));
+        let post = get_post(&post_id, &author, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!(post.reaction, None);
+
+        let mut tx = create_tx!(conn);
+        assert!(remove_reaction(&viewer, &post_id, None, &mut tx).await);
+        tx.commit().await.unwrap();
+        let post = get_post(&post_id, &viewer, &mut conn, &state, false)
+            .await
+            .unwrap();
+        assert_eq!((post.dislikes_count, post.reaction), (0, None));
+
+        cleanup(&[&author, &viewer], &mut conn).await;
+    }
+}
@@ -0,0 +1,56 @@
+use fred::prelude::{KeysInterface, SortedSetsInterface};
+
+use crate::utils::state::ArcAppState;
+
+/// Amount of posts ranked for one "for you" snapshot
+pub const RECOMMENDATIONS_SIZE: i64 = 500;
+/// Snapshot is rebuilt when client starts from the top, TTL only bounds paging
+const SNAPSHOT_TTL: i64 = 1800;
+
+fn snapshot_key(user_id: &str, snapshot: &str) -> String {
+    format!("foryou:{}:{}", user_id, snapshot)
+}
+
+/// Stores user's ranking as snapshot, ranking items are (post_id, score)
+/// Snapshots are only a cache, so errors are ignored
+pub async fn store_recommendations(
+    user_id: &str,
+    snapshot: &str,
+    ranking: &[(String, f64)],
+    state: &ArcAppState,
+) {
+    if ranking.is_empty() {
+        return;
+    }
+    let values: Vec<(f64, &str)> = ranking
+        .iter()
+        .map(|(post_id, score)| (*score, post_id.as_str()))
+        .collect();
+
+    let key = snapshot_key(user_id, snapshot);
+    let pipeline = state.cache_redis.pipeline();
+    let _: Result<(), _> = pipeline.zadd(&key, None, None, false, false, values).await;
+    let _: Result<(), _> = pipeline.expire(&key, SNAPSHOT_TTL, None).await;
+    let _: Result<(), _> = pipeline.all().await;
+}
+
+/// Get 'count' post ids of user's snapshot starting at 'offset', best first
+/// Returns None if snapshot expired
+pub async fn get_recommendations_page(
+    user_id: &str,
+    snapshot: &str,
+    offset: i64,
+    count: i64,
+    state: &ArcAppState,
+) -> Option<Vec<String>> {
+    let key = snapshot_key(user_id, snapshot);
+    let exists: i64 = state.cache_redis.exists(&key).await.ok()?;
+    if exists == 0 {
+        return None;
+    }
+    state
+        .cache_redis
+        .zrange(&key, offset, offset + count - 1, None, true, None, false)
+        .await
+        .ok()
+}
@@ -0,0 +1,333 @@
+use deadpool_postgres::Transaction;
+use fred::prelude::{Expiration, KeysInterface};
+use tokio_postgres::Row;
+
+use crate::{
+    database::conn::LazyConn,
+    entities::role::Role,
+    utils::{
+        perms::{Permission, permissions_from_names, permissions_to_list},
+        state::ArcAppState,
+    },
+};
+
+const ROLE_CACHE_TTL: i64 = 600;
+
+fn cache_key(role_id: &i32) -> String {
+    format!("role_perms:{}", role_id)
+}
+
+fn user_cache_key(user_id: &str) -> String {
+    format!("user_role:{}", user_id)
+}
+
+/// Private function for converting Row to Role
+/// Row needs to have role_id, name, rank and permissions (array of names)
+fn row_to_role(row: Row) -> Role {
+    let names: Vec<String> = row.get("permissions");
+    Role {
+        role_id: row.get("role_id"),
+        name: row.get("name"),
+        rank: row.get("rank"),
+        permissions: permissions_to_list(permissions_from_names(&names)),
+    }
+}
+
+/// Get permissions of role, cached in cache_redis
+/// Unknown roles have no permissions
+pub async fn get_role_permissions(
+    role_id: &i32,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Permission {
+    let key = cache_key(role_id);
+    let cached: Option<u32> = state.cache_redis.get(&key).await.unwrap_or(None);
+    if let Some(bits) = cached {
+        return Permission::from_bits_truncate(bits);
+    }
+
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            SELECT permission FROM role_permissions
+            WHERE role_id = $1
+            ",
+            &[role_id],
+        )
+        .await
+        .unwrap();
+    let names: Vec<String> = rows.into_iter().map(|r| r.get("permission")).collect();
+    let permissions = permissions_from_names(&names);
+
+    // Cache is only an optimization, so errors are ignored
+    let _: Result<(), _> = state
+        .cache_redis
+        .set(
+            &key,
+            permissions.bits(),
+            Some(Expiration::EX(ROLE_CACHE_TTL)),
+            None,
+            false,
+        )
+        .await;
+
+    permissions
+}
+
+/// Get role_id of user, cached in cache_redis
+pub async fn get_user_role_id(
+    user_id: &String,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Option<i32> {
+    let key = user_cache_key(user_id);
+    let cached: Option<i32> = state.cache_redis.get(&key).await.unwrap_or(None);
+    if cached.is_some() {
+        return cached;
+    }
+
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "
+            SELECT COALESCE(role_id, 0) AS role_id FROM users
+            WHERE user_id = $1
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    let role_id: i32 = row?.get("role_id");
+
+    let _: Result<(), _> = state
+        .cache_redis
+        .set(
+            &key,
+            role_id,
+            Some(Expiration::EX(ROLE_CACHE_TTL)),
+            None,
+            false,
+        )
+        .await;
+    Some(role_id)
+}
+
+/// Get permissions of user through their role
+pub async fn get_user_permissions(
+    user_id: &String,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Option<Permission> {
+    let role_id = get_user_role_id(user_id, conn, state).await?;
+    Some(get_role_permissions(&role_id, conn, state).await)
+}
+
+/// Removes cached permissions of role
+pub async fn invalidate_role_cache(role_id: &i32, state: &ArcAppState) {
+    let _: Result<i64, _> = state.cache_redis.del(cache_key(role_id)).await;
+}
+
+/// Removes cached role of user, has to be called after every change of users.role_id
+pub async fn invalidate_user_role_cache(user_id: &str, state: &ArcAppState) {
+    let _: Result<i64, _> = state.cache_redis.del(user_cache_key(user_id)).await;
+}
+
+/// Get all roles with their permissions
+pub async fn get_roles(conn: &mut LazyConn) -> Vec<Role> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            SELECT r.role_id, r.name, r.rank,
+                   COALESCE(
+                       array_agg(rp.permission)
+                       FILTER (WHERE rp.permission IS NOT NULL),
+                       '{}'
+                   ) AS permissions
+            FROM roles r
+            LEFT JOIN role_permissions rp ON rp.role_id = r.role_id
+            GROUP BY r.role_id
+            ORDER BY r.rank, r.role_id
+            ",
+            &[],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(row_to_role).collect()
+}
+
+/// Get rank of role, None if role doesn't exist
+pub async fn get_role_rank(role_id: &i32, conn: &mut LazyConn) -> Option<i32> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt("SELECT rank FROM roles WHERE role_id = $1", &[role_id])
+        .await
+        .unwrap();
+    row.map(|r| r.get("rank"))
+}
+
+/// Get rank of user's role, users with unknown role rank as plain users
+pub async fn get_user_rank(
+    user_id: &String,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Option<i32> {
+    let role_id = get_user_role_id(user_id, conn, state).await?;
+    Some(get_role_rank(&role_id, conn).await.unwrap_or(0))
+}
+
+/// Check if role name is taken
+pub async fn role_name_exists(name: &String, conn: &mut LazyConn) -> bool {
+    let db = conn.get_client().await.unwrap();
+    let value = db
+        .query_opt("SELECT 1 FROM roles WHERE name = $1", &[name])
+        .await
+        .unwrap();
+    value.is_some()
+}
+
+/// Creates role of given rank with the next free id below owner role (999)
+/// Roles table is locked, so concurrent creations don't pick the same id
+/// Returns None if name is already taken
+pub async fn create_role(
+    name: &String,
+    rank: i32,
+    permissions: Permission,
+    tx: &mut Transaction<'_>,
+) -> Option<i32> {
+    tx.execute("LOCK TABLE roles IN SHARE ROW EXCLUSIVE MODE", &[])
+        .await
+        .unwrap();
+    let row = tx
+        .query_opt(
+            "
+            INSERT INTO roles (role_id, name, rank)
+            SELECT COALESCE(MAX(role_id) FILTER (WHERE role_id < 999), -1) + 1, $1, $2
+            FROM roles
+            ON CONFLICT (name) DO NOTHING
+            RETURNING role_id
+            ",
+            &[name, &rank],
+        )
+        .await
+        .unwrap();
+    let role_id: i32 = row?.get("role_id");
+
+    let names: Vec<&str> = permissions_to_list(permissions);
+    tx.execute(
+        "
+        INSERT INTO role_permissions (role_id, permission)
+        SELECT $1, unnest($2::TEXT[])
+        ",
+        &[&role_id, &names],
+    )
+    .await
+    .unwrap();
+    Some(role_id)
+}
+
+/// Sets role of user, returns false if nothing changed
+/// Cached role isn't touched here, call invalidate_user_role_cache after commit
+pub async fn set_user_role(user_id: &String, role_id: &i32, tx: &mut Transaction<'_>) -> bool {
+    let affected = tx
+        .execute(
+            "
+            UPDATE users SET role_id = $2
+            WHERE user_id = $1 AND COALESCE(role_id, 0) <> $2
+            ",
+            &[user_id, role_id],
+        )
+        .await
+        .unwrap();
+    affected > 0
+}
+
+/// Enables or disables single permission of role
+/// 'permission' is a name of Permission flag, returns false if nothing changed
+pub async fn set_role_permission(
+    role_id: &i32,
+    permission: &str,
+    enabled: bool,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let sql = if enabled {
+        "
+        INSERT INTO role_permissions (role_id, permission)
+        VALUES ($1, $2)
+        ON CONFLICT (role_id, permission) DO NOTHING
+        "
+    } else {
+        "
+        DELETE FROM role_permissions
+        WHERE role_id = $1 AND permission = $2
+        "
+    };
+    let affected = tx.execute(sql, &[role_id, &permission]).await.unwrap();
+    affected > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::test_support::{cleanup, new_user},
+        get_conn,
+        utils::{state::AppState, thread_state::generate_id},
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn concurrent_roles_get_distinct_ids() {
+        let state = Arc::new(AppState::for_tests());
+        let names = [
+            format!("role_{}", generate_id()),
+            format!("role_{}", generate_id()),
+        ];
+
+        let create = |name: String| {
+            let state = state.clone();
+            async move {
+                let mut conn = get_conn!(state);
+                let mut tx = create_tx!(conn);
+                let role_id = create_role(&name, 350, Permission::RED_BUTTON, &mut tx).await;
+                tx.commit().await.unwrap();
+                role_id
+            }
+        };
+        let (first, second) = tokio::join!(create(names[0].clone()), create(names[1].clone()));
+        let (first, second) = (first.unwrap(), second.unwrap());
+        assert_ne!(first, second);
+        assert!(first < 999 && second < 999);
+        // Taken name is reported instead of failing
+        assert_eq!(create(names[0].clone()).await, None);
+
+        let mut conn = get_conn!(state);
+        let db = conn.get_client().await.unwrap();
+        db.execute("DELETE FROM roles WHERE name = ANY($1)", &[&names.to_vec()])
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn changed_user_role_is_not_served_from_cache() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        let mut conn = get_conn!(state);
+        let user_id = new_user(&mut conn).await;
+
+        assert_eq!(get_user_role_id(&user_id, &mut conn, &state).await, Some(0));
+        let mut tx = create_tx!(conn);
+        assert!(set_user_role(&user_id, &3, &mut tx).await);
+        assert!(!set_user_role(&user_id, &3, &mut tx).await);
+        tx.commit().await.unwrap();
+        invalidate_user_role_cache(&user_id, &state).await;
+        assert_eq!(get_user_role_id(&user_id, &mut conn, &state).await, Some(3));
+
+        invalidate_user_role_cache(&user_id, &state).await;
+        cleanup(&[&user_id], &mut conn).await;
+    }
+}
@@ -0,0 +1,207 @@
+use deadpool_postgres::Transaction;
+use fred::prelude::{Expiration, KeysInterface};
+use tokio_postgres::{Row, types::Json};
+
+use crate::{
+    database::conn::LazyConn, entities::settings::UserSettings, utils::state::ArcAppState,
+};
+
+const SETTINGS_CACHE_TTL: i64 = 3600;
+
+fn cache_key(user_id: &str) -> String {
+    format!("settings:{}", user_id)
+}
+
+/// Private function to get user_id and settings from Row, broken settings fall back to defaults
+fn row_to_settings(row: Row) -> (String, UserSettings) {
+    let settings = row
+        .get::<_, Option<serde_json::Value>>("settings")
+        .and_then(|s| serde_json::from_value(s).ok())
+        .unwrap_or_default();
+    (row.get("user_id"), settings)
+}
+
+/// Get user settings, cached in cache_redis
+/// Returns defaults if user never changed settings
+pub async fn get_user_settings(
+    user_id: &String,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> UserSettings {
+    let key = cache_key(user_id);
+    let cached: Option<String> = state.cache_redis.get(&key).await.unwrap_or(None);
+    if let Some(settings) = cached.and_then(|s| serde_json::from_str(&s).ok()) {
+        return settings;
+    }
+
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "
+            SELECT settings FROM user_settings
+            WHERE user_id = $1
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+
+    let settings: UserSettings = row
+        .and_then(|r| serde_json::from_value(r.get("settings")).ok())
+        .unwrap_or_default();
+
+    // Cache is only an optimization, so errors are ignored
+    let _: Result<(), _> = state
+        .cache_redis
+        .set(
+            &key,
+            serde_json::to_string(&settings).unwrap(),
+            Some(Expiration::EX(SETTINGS_CACHE_TTL)),
+            None,
+            false,
+        )
+        .await;
+
+    settings
+}
+
+/// Get settings of users whose full profile viewer can see, in one query without cache
+/// Private accounts are left out unless viewer is the user or approved follower
+/// Users who never changed settings get defaults
+pub async fn get_visible_users_settings(
+    viewer_id: &String,
+    user_ids: &[String],
+    conn: &mut LazyConn,
+) -> Vec<(String, UserSettings)> {
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            SELECT u.user_id, s.settings FROM users u
+            LEFT JOIN user_profiles up ON up.user_id = u.user_id
+            LEFT JOIN user_settings s ON s.user_id = u.user_id
+            WHERE u.user_id = ANY($2)
+              AND (
+                  u.user_id = $1
+                  OR NOT COALESCE(up.is_private, FALSE)
+                  OR EXISTS (
+                      SELECT 1 FROM followed f
+                      WHERE f.user_id = $1 AND f.followed_to = u.user_id
+                  )
+              )
+            ",
+            &[viewer_id, &user_ids],
+        )
+        .await
+        .unwrap();
+
+    rows.into_iter().map(row_to_settings).collect()
+}
+
+/// Get settings of users in one query without cache, for notifying many users at once
+/// Users who never changed settings get defaults
+pub async fn get_users_settings(
+    user_ids: &[String],
+    tx: &mut Transaction<'_>,
+) -> Vec<(String, UserSettings)> {
+    let rows = tx
+        .query(
+            "
+            SELECT u.user_id, s.settings FROM users u
+            LEFT JOIN user_settings s ON s.user_id = u.user_id
+            WHERE u.user_id = ANY($1)
+            ",
+            &[&user_ids],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(row_to_settings).collect()
+}
+
+/// Replaces user settings
+/// Cache has to be invalidated with 'invalidate_settings_cache' after commit
+pub async fn update_user_settings(
+    user_id: &String,
+    settings: &UserSettings,
+    tx: &mut Transaction<'_>,
+) {
+    tx.execute(
+        "
+        INSERT INTO user_settings (user_id, settings)
+        VALUES ($1, $2)
+        ON CONFLICT (user_id) DO UPDATE SET settings = EXCLUDED.settings
+        ",
+        &[user_id, &Json(settings)],
+    )
+    .await
+    .unwrap();
+}
+
+/// Removes cached settings of user
+pub async fn invalidate_settings_cache(user_id: &str, state: &ArcAppState) {
+    let _: Result<i64, _> = state.cache_redis.del(cache_key(user_id)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::{
+            follows::follow_user,
+            test_support::{cleanup, new_user},
+        },
+        get_conn,
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn private_settings_are_visible_to_followers() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let viewer = new_user(&mut conn).await;
+        let public = new_user(&mut conn).await;
+        let followed = new_user(&mut conn).await;
+        let stranger = new_user(&mut conn).await;
+
+        let mut hidden = UserSettings::default();
+        hidden.privacy.show_presence = false;
+        let mut tx = create_tx!(conn);
+        update_user_settings(&public, &hidden, &mut tx).await;
+        follow_user(&viewer, &followed, &mut tx).await;
+        for user_id in [&viewer, &followed, &stranger] {
+            tx.execute(
+                "INSERT INTO user_profiles (user_id, is_private) VALUES ($1, TRUE)",
+                &[user_id],
+            )
+            .await
+            .unwrap();
+        }
+        tx.commit().await.unwrap();
+
+        let user_ids = vec![
+            viewer.clone(),
+            public.clone(),
+            followed.clone(),
+            stranger.clone(),
+        ];
+        let mut settings = get_visible_users_settings(&viewer, &user_ids, &mut conn).await;
+        settings.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut expected = vec![
+            (viewer.clone(), true),
+            (public.clone(), false),
+            (followed.clone(), true),
+        ];
+        expected.sort();
+        let visible: Vec<(String, bool)> = settings
+            .into_iter()
+            .map(|(user_id, s)| (user_id, s.privacy.show_presence))
+            .collect();
+        assert_eq!(visible, expected);
+
+        cleanup(&[&viewer, &public, &followed, &stranger], &mut conn).await;
+    }
+}
@@ -0,0 +1,191 @@
+use fred::prelude::{Expiration, KeysInterface};
+use tokio_postgres::Row;
+
+use crate::{
+    database::conn::LazyConn,
+    entities::tag::{Tag, TrendingTag},
+    utils::state::ArcAppState,
+};
+
+const TRENDING_CACHE_TTL: i64 = 300;
+/// Tags need this many posts in the window to trend, so a couple of posts can't top the list
+const TRENDING_MIN_POSTS: i64 = 3;
+const TRENDING_LIMIT: i64 = 20;
+
+/// Private function for converting Row to Tag
+fn row_to_tag(row: Row) -> Tag {
+    Tag {
+        tag_id: row.get("tag_id"),
+        name: row.get("name"),
+        posts_count: row.get("posts_count"),
+        created_at: row.get("created_at"),
+    }
+}
+
+/// Get tag by normalized name
+pub async fn get_tag(name: &String, conn: &mut LazyConn) -> Option<Tag> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "
+            SELECT tag_id, name, COALESCE(posts_count, 0) AS posts_count,
+                   EXTRACT(EPOCH FROM created_at)::BIGINT AS created_at
+            FROM tags WHERE name = $1
+            ",
+            &[name],
+        )
+        .await
+        .unwrap();
+    row.map(row_to_tag)
+}
+
+/// Get tags starting with normalized 'prefix', most used first
+pub async fn search_tags(prefix: &str, limit: i64, conn: &mut LazyConn) -> Vec<Tag> {
+    let db = conn.get_client().await.unwrap();
+    // '_' is allowed in tags, but it's a wildcard in LIKE
+    let pattern = format!("{}%", prefix.replace('_', "\\_"));
+    let rows = db
+        .query(
+            "
+            SELECT tag_id, name, COALESCE(posts_count, 0) AS posts_count,
+                   EXTRACT(EPOCH FROM created_at)::BIGINT AS created_at
+            FROM tags
+            WHERE name LIKE $1 AND posts_count > 0
+            ORDER BY posts_count DESC, name
+            LIMIT $2
+            ",
+            &[&pattern, &limit],
+        )
+        .await
+        .unwrap();
+    rows.into_iter().map(row_to_tag).collect()
+}
+
+/// Get tags growing fastest in the last 'window_hours' compared to the window before it
+/// Posts of private accounts and deleted posts aren't counted, result is cached in cache_redis
+pub async fn get_trending_tags(
+    window_hours: i64,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Vec<TrendingTag> {
+    let key = format!("trending_tags:{}", window_hours);
+    let cached: Option<String> = state.cache_redis.get(&key).await.unwrap_or(None);
+    if let Some(tags) = cached.and_then(|s| serde_json::from_str(&s).ok()) {
+        return tags;
+    }
+
+    let db = conn.get_client().await.unwrap();
+    let rows = db
+        .query(
+            "
+            WITH usage AS (
+                SELECT pt.tag_id,
+                       COUNT(*) FILTER (
+                           WHERE p.created_at > now() - $1::BIGINT * INTERVAL '1 hour'
+                       ) AS recent_count,
+                       COUNT(*) FILTER (
+                           WHERE p.created_at <= now() - $1::BIGINT * INTERVAL '1 hour'
+                       ) AS previous_count
+                FROM post_tags pt
+                JOIN posts p ON p.post_id = pt.post_id
+                LEFT JOIN user_profiles up ON up.user_id = p.user_id
+                WHERE p.created_at > now() - 2 * $1::BIGINT * INTERVAL '1 hour'
+                  AND NOT COALESCE(p.is_deleted, FALSE)
+                  AND COALESCE(p.status, 'active') NOT IN ('draft', 'scheduled')
+                  AND p.visibility = 'public'
+                  AND NOT COALESCE(up.is_private, FALSE)
+                GROUP BY pt.tag_id
+            )
+            SELECT t.name, u.recent_count, u.previous_count,
+                   u.recent_count::FLOAT8 / (u.previous_count + 2) AS score
+            FROM usage u
+            JOIN tags t ON t.tag_id = u.tag_id
+            WHERE u.recent_count >= $2
+            ORDER BY score DESC, u.recent_count DESC
+            LIMIT $3
+            ",
+            &[&window_hours, &TRENDING_MIN_POSTS, &TRENDING_LIMIT],
+        )
+        .await
+        .unwrap();
+    let tags: Vec<TrendingTag> = rows
+        .into_iter()
+        .map(|r| TrendingTag {
+            name: r.get("name"),
+            recent_count: r.get("recent_count"),
+            previous_count: r.get("previous_count"),
+            score: r.get("score"),
+        })
+        .collect();
+
+    // Cache is only an optimization, so errors are ignored
+    let _: Result<(), _> = state
+        .cache_redis
+        .set(
+            &key,
+            serde_json::to_string(&tags).unwrap(),
+            Some(Expiration::EX(TRENDING_CACHE_TTL)),
+            None,
+            false,
+        )
+        .await;
+
+    tags
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        database::{
+            posts::get_tag_posts,
+            test_support::{cleanup, ids, new_post, new_user},
+        },
+        get_conn,
+        utils::{pagination::CursorParams, state::AppState, thread_state::generate_id},
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn tags_are_browsable() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let viewer = new_user(&mut conn).await;
+        let tag = format!("trend_{}", generate_id());
+
+        let old = new_post(&author, &[&tag], &mut conn).await;
+        let db = conn.get_client().await.unwrap();
+        db.execute(
+            "UPDATE posts SET created_at = now() - INTERVAL '30 hours' WHERE post_id = $1",
+            &[&old],
+        )
+        .await
+        .unwrap();
+        let mut recent = Vec::new();
+        for _ in 0..3 {
+            recent.push(new_post(&author, &[&tag], &mut conn).await);
+        }
+
+        let found = get_tag(&tag, &mut conn).await.unwrap();
+        assert_eq!(found.posts_count, 4);
+        let prefix = &tag[..tag.len() - 3];
+        let names: Vec<String> = search_tags(prefix, 10, &mut conn)
+            .await
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, vec![tag.clone()]);
+
+        let cursor = CursorParams {
+            limit: Some(2),
+            ..Default::default()
+        };
+        let posts = get_tag_posts(&found.tag_id, &viewer, &cursor, &mut conn, &state).await;
+        assert_eq!(ids(&posts), vec![recent[2].as_str(), recent[1].as_str()]);
+
+        cleanup(&[&author, &viewer], &mut conn).await;
+    }
+}
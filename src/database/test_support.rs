@@ -0,0 +1,53 @@
+use crate::{
+    create_tx,
+    database::{
+        auth::create_user,
+        conn::LazyConn,
+        posts::{create_post, set_post_tags},
+    },
+    entities::post::Post,
+    utils::thread_state::generate_id,
+};
+
+/// Creates user with unique name
+pub async fn new_user(conn: &mut LazyConn) -> String {
+    let name = format!("test_{}", generate_id());
+    let email = format!("{}@test.local", name);
+    let mut tx = create_tx!(conn);
+    let user_id = create_user(&name, &email, "password".to_string(), &mut tx).await;
+    tx.commit().await.unwrap();
+    user_id
+}
+
+/// Creates public post of user with given tags
+pub async fn new_post(user_id: &String, tags: &[&str], conn: &mut LazyConn) -> String {
+    let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+    let mut tx = create_tx!(conn);
+    let post_id = create_post(
+        user_id,
+        &"content".to_string(),
+        &None,
+        &vec!["spoiler".to_string()],
+        &None,
+        "public",
+        &mut tx,
+    )
+    .await;
+    set_post_tags(&post_id, &tags, &mut tx).await;
+    tx.commit().await.unwrap();
+    post_id
+}
+
+/// Deletes users, their posts and everything else goes with them
+pub async fn cleanup(user_ids: &[&String], conn: &mut LazyConn) {
+    let db = conn.get_client().await.unwrap();
+    for user_id in user_ids {
+        db.execute("DELETE FROM users WHERE user_id = $1", &[user_id])
+            .await
+            .unwrap();
+    }
+}
+
+pub fn ids(posts: &[Post]) -> Vec<&str> {
+    posts.iter().map(|p| p.post_id.as_str()).collect()
+}
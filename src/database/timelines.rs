@@ -0,0 +1,136 @@
+use fred::prelude::{KeysInterface, SortedSetsInterface};
+
+use crate::utils::{snowflake::SnowflakeGenerator, state::ArcAppState};
+
+/// Amount of newest posts kept in materialised timeline
+pub const TIMELINE_SIZE: i64 = 800;
+/// Timelines of inactive users expire and are rebuilt on next read
+const TIMELINE_TTL: i64 = 7 * 24 * 3600;
+
+fn timeline_key(user_id: &str) -> String {
+    format!("timeline:{}", user_id)
+}
+
+/// Score of post in timeline, publish time in ms is exact in f64 unlike the whole feed_id
+fn score(feed_id: i64) -> f64 {
+    SnowflakeGenerator::parse(feed_id as u64).0
+}
+
+/// Adds posts to timelines of users, timelines are trimmed to TIMELINE_SIZE newest posts
+/// 'posts' are (post_id, feed_id), posts are ordered by feed_id as in feeds
+/// Timelines are only a cache, so errors are ignored
+pub async fn push_to_timelines(posts: &[(String, i64)], user_ids: &[String], state: &ArcAppState) {
+    if posts.is_empty() || user_ids.is_empty() {
+        return;
+    }
+    let values: Vec<(f64, &str)> = posts
+        .iter()
+        .map(|(post_id, feed_id)| (score(*feed_id), post_id.as_str()))
+        .collect();
+
+    let pipeline = state.cache_redis.pipeline();
+    for user_id in user_ids {
+        let key = timeline_key(user_id);
+        let _: Result<(), _> = pipeline
+            .zadd(&key, None, None, false, false, values.clone())
+            .await;
+        let _: Result<(), _> = pipeline
+            .zremrangebyrank(&key, 0, -(TIMELINE_SIZE + 1))
+            .await;
+        let _: Result<(), _> = pipeline.expire(&key, TIMELINE_TTL, None).await;
+    }
+    let _: Result<(), _> = pipeline.all().await;
+}
+
+/// Get up to 'count' post ids from user's timeline published not later than feed_id 'before',
+/// newest first
+/// Returns None if timeline isn't materialised
+pub async fn get_timeline(
+    user_id: &str,
+    before: Option<i64>,
+    count: i64,
+    state: &ArcAppState,
+) -> Option<Vec<String>> {
+    let key = timeline_key(user_id);
+    let exists: i64 = state.cache_redis.exists(&key).await.ok()?;
+    if exists == 0 {
+        return None;
+    }
+
+    // Score only has ms precision, exact bound is applied in SQL
+    // Bound has to stay f64, fred sends numeric strings as lexicographic bounds
+    let max = before.map_or(f64::INFINITY, score);
+    state
+        .cache_redis
+        .zrevrangebyscore(&key, max, f64::NEG_INFINITY, false, Some((0, count)))
+        .await
+        .ok()
+}
+
+/// Drops materialised timeline, used when followed accounts change
+pub async fn invalidate_timeline(user_id: &str, state: &ArcAppState) {
+    let _: Result<i64, _> = state.cache_redis.del(timeline_key(user_id)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use super::*;
+    use crate::utils::{state::AppState, thread_state::generate_id};
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn timelines_keep_newest_posts() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        let user_id = generate_id().to_string();
+        assert_eq!(get_timeline(&user_id, None, 10, &state).await, None);
+
+        let posts: Vec<(String, i64)> = (0..=TIMELINE_SIZE)
+            .map(|_| {
+                let id = generate_id();
+                (id.to_string(), id as i64)
+            })
+            .collect();
+        push_to_timelines(&posts, std::slice::from_ref(&user_id), &state).await;
+        let newest: Vec<String> = posts.iter().rev().take(3).map(|p| p.0.clone()).collect();
+        assert_eq!(get_timeline(&user_id, None, 3, &state).await, Some(newest));
+        let kept = get_timeline(&user_id, None, TIMELINE_SIZE + 1, &state)
+            .await
+            .unwrap();
+        assert_eq!(kept.len() as i64, TIMELINE_SIZE);
+        assert!(!kept.contains(&posts[0].0));
+
+        invalidate_timeline(&user_id, &state).await;
+        assert_eq!(get_timeline(&user_id, None, 10, &state).await, None);
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn timeline_pages_end_before_cursor() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        let users = [generate_id().to_string(), generate_id().to_string()];
+        let old = generate_id() as i64;
+        // Scores have ms precision, so posts are published in different ms
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let new = generate_id() as i64;
+        // Draft with the oldest id that was published last is ordered by its feed_id
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let published = (old - 1, generate_id() as i64);
+        let posts = [
+            (old.to_string(), old),
+            (new.to_string(), new),
+            (published.0.to_string(), published.1),
+        ];
+        push_to_timelines(&posts, &users, &state).await;
+
+        for user_id in &users {
+            let page = get_timeline(user_id, Some(old), 10, &state).await;
+            assert_eq!(page, Some(vec![old.to_string()]));
+            let page = get_timeline(user_id, None, 10, &state).await;
+            let newest = vec![published.0.to_string(), new.to_string(), old.to_string()];
+            assert_eq!(page, Some(newest));
+            invalidate_timeline(user_id, &state).await;
+        }
+    }
+}
@@ -1,9 +1,10 @@
 use deadpool_postgres::Transaction;
-use tokio_postgres::{Row, types::ToSql};
+use tokio_postgres::{Row, error::SqlState, types::ToSql};
 
 use crate::{database::conn::LazyConn, entities::user::User, utils::storage::normalize_url};
 
 /// Private function for converting Row to User
+/// Columns missing in row (e.g. for minimized user) become None
 fn row_to_user(row: Row) -> User {
     User {
         user_id: row.get("user_id"),
@@ -11,12 +12,13 @@ fn row_to_user(row: Row) -> User {
         role_id: row.get("role_id"),
         display_name: row.get("display_name"),
         avatar_url: normalize_url(row.get("avatar_url")),
-        banner_url: normalize_url(row.get("banner_url")),
-        bio: row.get("bio"),
-        badges: row.get("badges"),
-        languages: row.get("languages"),
-        following_count: row.get("following_count"),
-        followers_count: row.get("followers_count"),
+        banner_url: normalize_url(row.try_get("banner_url").unwrap_or(None)),
+        bio: row.try_get("bio").unwrap_or(None),
+        badges: row.try_get("badges").unwrap_or(None),
+        languages: row.try_get("languages").unwrap_or(None),
+        following_count: row.try_get("following_count").unwrap_or(None),
+        followers_count: row.try_get("followers_count").unwrap_or(None),
+        is_private: row.get("is_private"),
     }
 }
 
@@ -25,7 +27,8 @@ pub async fn get_min_user(user_id: &String, conn: &mut LazyConn) -> Option<User>
     let db = conn.get_client().await.unwrap();
     let sql = "
         SELECT u.user_id, u.username, p.display_name, u.role_id,
-               ac.objects[1] as avatar_url
+               ac.objects[1] as avatar_url,
+               COALESCE(p.is_private, FALSE) as is_private
         FROM users u
         LEFT JOIN user_profiles p ON u.user_id = p.user_id
         LEFT JOIN files ac ON ac.context_id = p.avatar_context_id
@@ -42,7 +45,8 @@ pub async fn get_user(user_id: &String, conn: &mut LazyConn) -> Option<User> {
         SELECT u.user_id, u.username, p.display_name, u.role_id,
                ac.objects[1] as avatar_url,
                bc.objects[1] as banner_url, p.bio, p.badges, p.languages,
-               u.following_count, u.followers_count
+               u.following_count, u.followers_count,
+               COALESCE(p.is_private, FALSE) as is_private
         FROM users u
         LEFT JOIN user_profiles p ON u.user_id = p.user_id
         LEFT JOIN files ac ON ac.context_id = p.avatar_context_id
@@ -60,6 +64,7 @@ pub struct UserProfileUpdate {
     pub banner_context_id: Option<String>,
     pub bio: Option<String>,
     pub languages: Option<Vec<String>>,
+    pub is_private: Option<bool>,
 }
 
 /// Private function for 'update_user_profile'
@@ -104,6 +109,7 @@ pub async fn update_user_profile(
     );
     push_opt(&update.bio, "bio", &mut columns, &mut values);
     push_opt(&update.languages, "languages", &mut columns, &mut values);
+    push_opt(&update.is_private, "is_private", &mut columns, &mut values);
 
     if columns.is_empty() {
         return false;
@@ -136,3 +142,138 @@ pub async fn update_user_profile(
     tx.execute(query.as_str(), &params).await.unwrap();
     true
 }
+
+/// Get username and time of its last change, row of user stays locked until transaction ends
+/// Concurrent renames of the same user wait for each other, so cooldown can't be skipped
+pub async fn lock_username(
+    user_id: &String,
+    tx: &mut Transaction<'_>,
+) -> Option<(String, Option<i64>)> {
+    let row = tx
+        .query_opt(
+            "
+            SELECT username, EXTRACT(EPOCH FROM username_changed_at)::BIGINT AS changed_at
+            FROM users
+            WHERE user_id = $1
+            FOR UPDATE
+            ",
+            &[user_id],
+        )
+        .await
+        .unwrap();
+    row.map(|r| (r.get("username"), r.get("changed_at")))
+}
+
+/// Changes username and keeps the old one reserved for 'reserve_days'
+/// Returns false if username was taken in the meantime, transaction can't be used after that
+/// Consider checking cooldown with lock_username and username existence before using this func
+pub async fn change_username(
+    user_id: &String,
+    old_username: &String,
+    new_username: &String,
+    reserve_days: i64,
+    tx: &mut Transaction<'_>,
+) -> bool {
+    let updated = tx
+        .execute(
+            "
+            UPDATE users
+            SET username = $1, username_changed_at = NOW()
+            WHERE user_id = $2
+            ",
+            &[new_username, user_id],
+        )
+        .await;
+    match updated {
+        Err(err) if err.code() == Some(&SqlState::UNIQUE_VIOLATION) => return false,
+        updated => updated.unwrap(),
+    };
+
+    tx.execute(
+        "
+        INSERT INTO username_history (username, user_id, reserved_until)
+        VALUES ($1, $2, NOW() + make_interval(days => $3::INT))
+        ",
+        &[old_username, user_id, &(reserve_days as i32)],
+    )
+    .await
+    .unwrap();
+    true
+}
+
+/// Resolve username to user_id, reserved old usernames point to their current owner
+/// Returns: (user_id, is_old_username)
+pub async fn resolve_username(username: &String, conn: &mut LazyConn) -> Option<(String, bool)> {
+    let db = conn.get_client().await.unwrap();
+    let row = db
+        .query_opt(
+            "
+            SELECT user_id, is_old FROM (
+                SELECT user_id, FALSE AS is_old, NOW() AS changed_at
+                FROM users
+                WHERE username = $1
+                UNION ALL
+                SELECT user_id, TRUE AS is_old, changed_at
+                FROM username_history
+                WHERE username = $1 AND reserved_until > NOW()
+            ) u
+            ORDER BY is_old, changed_at DESC
+            LIMIT 1
+            ",
+            &[username],
+        )
+        .await
+        .unwrap();
+    row.map(|r| (r.get("user_id"), r.get("is_old")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        create_tx,
+        database::test_support::{cleanup, new_user},
+        get_conn,
+        utils::{state::AppState, thread_state::generate_id},
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn concurrent_renames_to_same_username() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let users = [new_user(&mut conn).await, new_user(&mut conn).await];
+        let username = format!("taken_{}", generate_id());
+
+        let rename = |user_id: String| {
+            let state = state.clone();
+            let username = username.clone();
+            async move {
+                let mut conn = get_conn!(state);
+                let mut tx = create_tx!(conn);
+                let (old, changed_at) = lock_username(&user_id, &mut tx).await.unwrap();
+                assert_eq!(changed_at, None);
+                let changed = change_username(&user_id, &old, &username, 30, &mut tx).await;
+                if changed {
+                    tx.commit().await.unwrap();
+                }
+                changed
+            }
+        };
+        let (first, second) = tokio::join!(rename(users[0].clone()), rename(users[1].clone()));
+        assert!(first ^ second);
+
+        let owner = if first { &users[0] } else { &users[1] };
+        assert_eq!(
+            resolve_username(&username, &mut conn).await,
+            Some((owner.clone(), false))
+        );
+        let mut tx = create_tx!(conn);
+        assert!(lock_username(owner, &mut tx).await.unwrap().1.is_some());
+        drop(tx);
+
+        cleanup(&[&users[0], &users[1]], &mut conn).await;
+    }
+}
@@ -0,0 +1,167 @@
+use fred::prelude::{KeysInterface, ListInterface};
+
+use crate::{
+    database::conn::LazyConn,
+    utils::{state::ArcAppState, thread_state::generate_id},
+};
+
+/// Views waiting to be flushed, every item is "<timestamp>:<user_id>:<post_id>"
+const BUFFER_KEY: &str = "views:buffer";
+
+/// Buffered post view, timestamp is in seconds
+#[derive(Debug, PartialEq)]
+pub struct PostView {
+    pub user_id: String,
+    pub post_id: String,
+    pub timestamp: i64,
+}
+
+/// Adds views to buffer, returns false if Redis is unavailable
+pub async fn buffer_views(
+    user_id: &str,
+    post_ids: &[String],
+    timestamp: i64,
+    state: &ArcAppState,
+) -> bool {
+    let items: Vec<String> = post_ids
+        .iter()
+        .map(|post_id| format!("{}:{}:{}", timestamp, user_id, post_id))
+        .collect();
+    let result: Result<i64, _> = state.cache_redis.rpush(BUFFER_KEY, items).await;
+    result.is_ok()
+}
+
+/// Takes all buffered views, buffer is moved away atomically so views pushed meanwhile aren't lost
+/// Views taken by a flush that fails are dropped, views are only a hint for feeds
+pub async fn take_buffered_views(state: &ArcAppState) -> Vec<PostView> {
+    let key = format!("views:flushing:{}", generate_id());
+    // Fails when buffer is empty
+    let renamed: Result<(), _> = state.cache_redis.rename(BUFFER_KEY, &key).await;
+    if renamed.is_err() {
+        return Vec::new();
+    }
+
+    let items: Vec<String> = state
+        .cache_redis
+        .lrange(&key, 0, -1)
+        .await
+        .unwrap_or_default();
+    let _: Result<i64, _> = state.cache_redis.del(&key).await;
+
+    items
+        .iter()
+        .filter_map(|item| {
+            let mut parts = item.splitn(3, ':');
+            Some(PostView {
+                timestamp: parts.next()?.parse().ok()?,
+                user_id: parts.next()?.to_string(),
+                post_id: parts.next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Stores views in bulk, repeated views only move timestamp forward
+/// Views of posts or users that no longer exist and of unpublished posts are skipped
+pub async fn insert_views(views: &[PostView], conn: &mut LazyConn) -> u64 {
+    if views.is_empty() {
+        return 0;
+    }
+    let user_ids: Vec<&str> = views.iter().map(|v| v.user_id.as_str()).collect();
+    let post_ids: Vec<&str> = views.iter().map(|v| v.post_id.as_str()).collect();
+    let timestamps: Vec<f64> = views.iter().map(|v| v.timestamp as f64).collect();
+
+    let db = conn.get_client().await.unwrap();
+    db.execute(
+        "
+        INSERT INTO user_post_views (user_id, post_id, timestamp)
+        SELECT DISTINCT ON (v.user_id, v.post_id) v.user_id, v.post_id, to_timestamp(v.ts)
+        FROM unnest($1::TEXT[], $2::TEXT[], $3::FLOAT8[]) AS v(user_id, post_id, ts)
+        JOIN users u ON u.user_id = v.user_id
+        JOIN posts p ON p.post_id = v.post_id
+            AND COALESCE(p.status, 'active') = 'active'
+        ORDER BY v.user_id, v.post_id, v.ts DESC
+        ON CONFLICT (user_id, post_id)
+        DO UPDATE SET timestamp = GREATEST(user_post_views.timestamp, EXCLUDED.timestamp)
+        ",
+        &[&user_ids, &post_ids, &timestamps],
+    )
+    .await
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        database::test_support::{cleanup, new_post, new_user},
+        get_conn,
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Postgres, see AppState::for_tests"]
+    async fn views_are_stored_once_per_post() {
+        let state = Arc::new(AppState::for_tests());
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let viewer = new_user(&mut conn).await;
+        let seen = new_post(&author, &[], &mut conn).await;
+        let other = new_post(&author, &[], &mut conn).await;
+
+        // Repeated views in one batch are stored once, views of unknown posts are skipped
+        let view = |post_id: &str, timestamp| PostView {
+            user_id: viewer.clone(),
+            post_id: post_id.to_string(),
+            timestamp,
+        };
+        let views = [
+            view(&seen, 1),
+            view(&seen, 3),
+            view(&other, 1),
+            view("0", 1),
+        ];
+        assert_eq!(insert_views(&views, &mut conn).await, 2);
+        // Older view doesn't move timestamp back
+        insert_views(&[view(&seen, 2)], &mut conn).await;
+
+        let db = conn.get_client().await.unwrap();
+        let timestamp: i64 = db
+            .query_one(
+                "
+                SELECT EXTRACT(EPOCH FROM timestamp)::BIGINT FROM user_post_views
+                WHERE user_id = $1 AND post_id = $2
+                ",
+                &[&viewer, &seen],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        assert_eq!(timestamp, 3);
+
+        cleanup(&[&author, &viewer], &mut conn).await;
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn buffered_views_are_taken_once() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        take_buffered_views(&state).await;
+
+        let post_ids = vec!["1".to_string(), "2".to_string()];
+        assert!(buffer_views("7", &post_ids, 100, &state).await);
+        let views = take_buffered_views(&state).await;
+        let expected: Vec<PostView> = post_ids
+            .into_iter()
+            .map(|post_id| PostView {
+                user_id: "7".to_string(),
+                post_id,
+                timestamp: 100,
+            })
+            .collect();
+        assert_eq!(views, expected);
+        assert!(take_buffered_views(&state).await.is_empty());
+    }
+}
@@ -0,0 +1,211 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, put},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    create_tx,
+    database::conn::LazyConn,
+    entities::badge::Badge,
+    extractors::perms::RequirePerm,
+    get_conn,
+    utils::{
+        perms::Permission,
+        response::{ApiResponse, AppError, FuncError, response},
+        state::ArcAppState,
+        validate::ValidatedJson,
+    },
+};
+
+type AdminSession = RequirePerm<{ Permission::ADMIN_PANEL.bits() }>;
+
+/// Badge catalog, public
+mod list {
+    use super::*;
+    use crate::database::badges::get_badges;
+
+    pub async fn handler(
+        State(state): State<ArcAppState>,
+    ) -> Result<ApiResponse<Vec<Badge>>, AppError> {
+        let mut conn = get_conn!(state);
+
+        let badges = get_badges(&mut conn).await;
+        Ok(response(badges, StatusCode::OK))
+    }
+}
+
+/// Single badge, public
+mod get_one {
+    use super::*;
+    use crate::database::badges::get_badge;
+
+    pub async fn handler(
+        State(state): State<ArcAppState>,
+        Path(badge_id): Path<i16>,
+    ) -> Result<ApiResponse<Badge>, AppError> {
+        let mut conn = get_conn!(state);
+
+        let badge = get_badge(&badge_id, &mut conn)
+            .await
+            .ok_or(FuncError::BadgeNotFound)?;
+        Ok(response(badge, StatusCode::OK))
+    }
+}
+
+/// Create new badge
+mod create {
+    use super::*;
+    use crate::{
+        database::{badges::create_badge, files::get_file_context},
+        entities::file::FileType,
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(min = 1, max = 32))]
+        name: String,
+        #[validate(length(max = 256))]
+        description: Option<String>,
+        icon_context_id: Option<String>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        pub badge_id: i16,
+    }
+
+    pub async fn handler(
+        admin: AdminSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        let mut conn = get_conn!(state);
+
+        if let Some(context_id) = &payload.icon_context_id {
+            let context = get_file_context(context_id, &mut conn)
+                .await
+                .ok_or(FuncError::InvalidFileContext)?;
+            if !context.is_usable_by(&admin.session.user_id, &[FileType::BadgeIcon]) {
+                return Err(FuncError::InvalidFileContext.into());
+            }
+        }
+
+        let mut tx = create_tx!(conn);
+        let badge_id = create_badge(
+            &payload.name,
+            &payload.description,
+            &payload.icon_context_id,
+            &mut tx,
+        )
+        .await
+        .ok_or(FuncError::BadgeExists)?;
+        tx.commit().await.unwrap();
+
+        Ok(response(Returns { badge_id }, StatusCode::CREATED))
+    }
+}
+
+/// Grant or revoke badge of user, every change is written to mod_audit
+mod toggle_user_badge {
+    use serde_json::json;
+
+    use super::*;
+    use crate::database::{
+        audit::{AuditEntry, create_audit_entry},
+        badges::{get_badge, get_user_badges, grant_badge, revoke_badge},
+        roles::get_user_role_id,
+        users::get_min_user,
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(min = 1, max = 512))]
+        reason: String,
+    }
+
+    async fn toggle(
+        granted: bool,
+        admin: AdminSession,
+        state: ArcAppState,
+        badge_id: i16,
+        user_id: String,
+        reason: String,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        if get_badge(&badge_id, &mut conn).await.is_none() {
+            return Err(FuncError::BadgeNotFound.into());
+        }
+        if get_min_user(&user_id, &mut conn).await.is_none() {
+            return Err(FuncError::UserNotFound.into());
+        }
+        let role_id = get_user_role_id(&admin.session.user_id, &mut conn, &state)
+            .await
+            .ok_or(FuncError::UserNotFound)?;
+
+        let mut tx = create_tx!(conn);
+        let old_badges = get_user_badges(&user_id, &mut tx).await.unwrap_or_default();
+        let changed = if granted {
+            grant_badge(&user_id, &badge_id, &mut tx).await
+        } else {
+            revoke_badge(&user_id, &badge_id, &mut tx).await
+        };
+
+        if changed {
+            create_audit_entry(
+                AuditEntry {
+                    user_id: &admin.session.user_id,
+                    towards_to: &user_id,
+                    role_id,
+                    target_type: "user",
+                    target_id: &user_id,
+                    action_type: if granted {
+                        "grant_badge"
+                    } else {
+                        "revoke_badge"
+                    },
+                    reason: &reason,
+                    metadata: Some(json!({ "badge_id": badge_id })),
+                    old_content: Some(json!({ "badges": old_badges })),
+                },
+                &mut tx,
+            )
+            .await;
+        }
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn grant(
+        admin: AdminSession,
+        State(state): State<ArcAppState>,
+        Path((badge_id, user_id)): Path<(i16, String)>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        toggle(true, admin, state, badge_id, user_id, payload.reason).await
+    }
+
+    pub async fn revoke(
+        admin: AdminSession,
+        State(state): State<ArcAppState>,
+        Path((badge_id, user_id)): Path<(i16, String)>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        toggle(false, admin, state, badge_id, user_id, payload.reason).await
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new()
+        .route("/", get(list::handler).post(create::handler))
+        .route("/{badge_id}", get(get_one::handler))
+        .route(
+            "/{badge_id}/users/{user_id}",
+            put(toggle_user_badge::grant).delete(toggle_user_badge::revoke),
+        )
+}
@@ -0,0 +1,140 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::put,
+};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    create_tx,
+    database::{comments::get_comment_post_id, conn::LazyConn, posts::get_post},
+    extractors::auth::AuthSession,
+    get_conn,
+    utils::{
+        response::{AppError, FuncError},
+        state::ArcAppState,
+        validate::ValidatedJson,
+    },
+};
+
+/// Private function for getting post of comment, comment has to be visible to viewer
+async fn get_visible_post_id(
+    comment_id: &String,
+    viewer_id: &str,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Result<String, FuncError> {
+    let post_id = get_comment_post_id(comment_id, conn)
+        .await
+        .ok_or(FuncError::CommentNotFound)?;
+    get_post(&post_id, viewer_id, conn, state, false)
+        .await
+        .ok_or(FuncError::CommentNotFound)?;
+    Ok(post_id)
+}
+
+/// Like or dislike comment, or remove own reaction
+mod reaction {
+    use super::*;
+    use crate::{
+        database::reactions::{remove_reaction, set_reaction},
+        entities::reaction::Reaction,
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        reaction: Reaction,
+    }
+
+    pub async fn put(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(comment_id): Path<String>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let post_id = get_visible_post_id(&comment_id, &session.user_id, &mut conn, &state).await?;
+
+        let mut tx = create_tx!(conn);
+        set_reaction(
+            &session.user_id,
+            &post_id,
+            Some(&comment_id),
+            payload.reaction,
+            &mut tx,
+        )
+        .await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn delete(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(comment_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let post_id = get_comment_post_id(&comment_id, &mut conn)
+            .await
+            .ok_or(FuncError::CommentNotFound)?;
+
+        let mut tx = create_tx!(conn);
+        remove_reaction(&session.user_id, &post_id, Some(&comment_id), &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Save comment to favorites or remove it from them
+mod favorite {
+    use super::*;
+    use crate::database::favorites::{add_favorite, remove_favorite};
+
+    pub async fn put(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(comment_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let post_id = get_visible_post_id(&comment_id, &session.user_id, &mut conn, &state).await?;
+
+        let mut tx = create_tx!(conn);
+        add_favorite(&session.user_id, &post_id, Some(&comment_id), &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn delete(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(comment_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let post_id = get_comment_post_id(&comment_id, &mut conn)
+            .await
+            .ok_or(FuncError::CommentNotFound)?;
+
+        let mut tx = create_tx!(conn);
+        remove_favorite(&session.user_id, &post_id, Some(&comment_id), &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new()
+        .route(
+            "/{comment_id}/reaction",
+            put(reaction::put).delete(reaction::delete),
+        )
+        .route(
+            "/{comment_id}/favorite",
+            put(favorite::put).delete(favorite::delete),
+        )
+}
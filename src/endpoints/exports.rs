@@ -0,0 +1,188 @@
+use axum::{
+    Router,
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    create_tx,
+    database::conn::LazyConn,
+    extractors::auth::AuthSession,
+    get_conn,
+    utils::{
+        response::{ApiResponse, AppError, FuncError, response},
+        security::{sign_url_token, verify_url_token},
+        state::ArcAppState,
+    },
+};
+
+/// Private function for message that is signed in download links
+fn download_message(export_id: &str, expires: i64) -> String {
+    format!("export|{}|{}", export_id, expires)
+}
+
+/// Request new personal data export
+mod create {
+    use chrono::Utc;
+    use validator::Validate;
+
+    use super::*;
+    use crate::{
+        database::exports::{create_export, get_last_export_time},
+        services::export::spawn_export,
+        utils::validate::ValidatedJson,
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[serde(default)]
+        zip: bool,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        pub export_id: String,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        let mut conn = get_conn!(state);
+
+        // Exports are heavy, so only one per cooldown
+        if let Some(last) = get_last_export_time(&session.user_id, &mut conn).await {
+            let cooldown = state.config.export_cooldown_hours * 3600;
+            if Utc::now().timestamp() - last < cooldown {
+                return Err(FuncError::ExportRateLimited.into());
+            }
+        }
+
+        let format = if payload.zip { "zip" } else { "json" };
+        let mut tx = create_tx!(conn);
+        let export_id = create_export(&session.user_id, format, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        spawn_export(
+            state.clone(),
+            export_id.clone(),
+            session.user_id,
+            payload.zip,
+        );
+
+        Ok(response(Returns { export_id }, StatusCode::ACCEPTED))
+    }
+}
+
+/// List of user's exports with download links for ready ones
+mod list {
+    use super::*;
+    use crate::database::exports::{DataExport, get_exports};
+
+    #[derive(Debug, Serialize)]
+    pub struct ExportItem {
+        #[serde(flatten)]
+        pub export: DataExport,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub download_url: Option<String>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+    ) -> Result<ApiResponse<Vec<ExportItem>>, AppError> {
+        let mut conn = get_conn!(state);
+        let exports = get_exports(&session.user_id, &mut conn).await;
+
+        let items = exports
+            .into_iter()
+            .map(|export| {
+                let download_url = match (export.status.as_str(), export.expires_at) {
+                    ("ready", Some(expires)) => {
+                        let token = sign_url_token(
+                            &download_message(&export.export_id, expires),
+                            &state.config.signature_key,
+                        );
+                        Some(format!(
+                            "/v1/exports/{}/download?expires={}&token={}",
+                            export.export_id, expires, token
+                        ))
+                    }
+                    _ => None,
+                };
+                ExportItem {
+                    export,
+                    download_url,
+                }
+            })
+            .collect();
+
+        Ok(response(items, StatusCode::OK))
+    }
+}
+
+/// Download export archive, authorized by signed link instead of headers
+mod download {
+    use axum::{
+        extract::{Path, Query},
+        http::header,
+        response::{IntoResponse, Response},
+    };
+    use chrono::Utc;
+
+    use super::*;
+    use crate::database::exports::get_export_archive;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Params {
+        expires: i64,
+        token: String,
+    }
+
+    pub async fn handler(
+        State(state): State<ArcAppState>,
+        Path(export_id): Path<String>,
+        Query(params): Query<Params>,
+    ) -> Result<Response, AppError> {
+        let message = download_message(&export_id, params.expires);
+        if !verify_url_token(&message, &params.token, &state.config.signature_key) {
+            return Err(FuncError::InvalidToken.into());
+        }
+        if params.expires < Utc::now().timestamp() {
+            return Err(FuncError::ExpiredToken.into());
+        }
+
+        let mut conn = get_conn!(state);
+        let (format, archive) = get_export_archive(&export_id, &mut conn)
+            .await
+            .ok_or(FuncError::ExportNotFound)?;
+
+        let content_type = if format == "zip" {
+            "application/zip"
+        } else {
+            "application/json"
+        };
+        let disposition = format!(
+            "attachment; filename=\"linkverse-export-{}.{}\"",
+            export_id, format
+        );
+
+        Ok((
+            [
+                (header::CONTENT_TYPE, content_type.to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            archive,
+        )
+            .into_response())
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new()
+        .route("/", post(create::handler).get(list::handler))
+        .route("/{export_id}/download", get(download::handler))
+}
@@ -0,0 +1,265 @@
+use axum::{
+    Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+};
+
+use crate::{
+    database::conn::LazyConn,
+    entities::post::Post,
+    extractors::auth::AuthSession,
+    get_conn,
+    utils::{
+        pagination::{CursorParams, Page},
+        response::{ApiResponse, AppError, FuncError, response},
+        state::ArcAppState,
+    },
+};
+
+/// Posts of followed accounts and own posts, newest first
+/// Only paging to older posts with 'before' is supported
+mod home {
+    use super::*;
+    use crate::{
+        database::{
+            follows::get_large_followed,
+            posts::{HomeSource, get_feed_id, get_home_posts},
+            timelines::get_timeline,
+        },
+        services::feed::rebuild_timeline,
+    };
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(cursor): Query<CursorParams>,
+    ) -> Result<ApiResponse<Page<Post>>, AppError> {
+        if cursor.after.is_some() {
+            return Err(FuncError::IncorrectData.into());
+        }
+        let limit = cursor.limit();
+        let mut conn = get_conn!(state);
+
+        // Cursor is post id, but published drafts are placed in feed by publish time
+        let before = match cursor.before {
+            Some(id) => Some(get_feed_id(&id.to_string(), &mut conn).await.unwrap_or(id)),
+            None => None,
+        };
+        let post_ids = match get_timeline(&session.user_id, before, limit + 1, &state).await {
+            Some(ids) => ids,
+            None => {
+                rebuild_timeline(&session.user_id, &mut conn, &state).await;
+                get_timeline(&session.user_id, before, limit + 1, &state)
+                    .await
+                    .unwrap_or_default()
+            }
+        };
+        // Timeline bound includes the cursor post, exact bound is applied in SQL
+        let post_ids: Vec<String> = post_ids
+            .into_iter()
+            .filter(|id| cursor.before.is_none_or(|before| *id != before.to_string()))
+            .take(limit as usize)
+            .collect();
+
+        let source = HomeSource {
+            oldest: post_ids.last().and_then(|id| id.parse().ok()),
+            exhausted: (post_ids.len() as i64) < limit,
+            large_ids: get_large_followed(
+                &session.user_id,
+                state.config.feed_fanout_limit,
+                &mut conn,
+            )
+            .await,
+            post_ids,
+        };
+        let posts =
+            get_home_posts(&session.user_id, &source, before, limit, &mut conn, &state).await;
+
+        // Page can be short when timeline posts were filtered out, but it isn't the end yet
+        let next_cursor = match posts.last() {
+            Some(last) if posts.len() as i64 == limit || !source.exhausted => {
+                Some(last.post_id.clone())
+            }
+            None if !source.exhausted => source.oldest.map(|id| id.to_string()),
+            _ => None,
+        };
+
+        Ok(response(
+            Page {
+                items: posts,
+                next_cursor,
+            },
+            StatusCode::OK,
+        ))
+    }
+}
+
+/// Recent posts ranked by popularity with time decay, best first
+/// Pages are read from the same snapshot, so ranking doesn't shift while paging
+mod popular {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::{
+        database::{
+            popular::{get_current_snapshot, get_snapshot_page},
+            posts::get_posts_by_ids,
+            users::get_user,
+        },
+        utils::pagination::{RankCursor, RankCursorParams},
+    };
+
+    #[derive(Debug, Deserialize)]
+    pub struct Params {
+        /// Only posts of authors speaking any of viewer's languages
+        #[serde(default)]
+        languages: bool,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(cursor): Query<RankCursorParams>,
+        Query(params): Query<Params>,
+    ) -> Result<ApiResponse<Page<Post>>, AppError> {
+        let limit = cursor.limit();
+        let mut conn = get_conn!(state);
+
+        let (snapshot, offset) = match cursor.cursor {
+            Some(RankCursor { snapshot, offset }) => (snapshot, offset),
+            None => match get_current_snapshot(&state).await {
+                Some(snapshot) => (snapshot, 0),
+                None => {
+                    return Ok(response(
+                        Page {
+                            items: Vec::new(),
+                            next_cursor: None,
+                        },
+                        StatusCode::OK,
+                    ));
+                }
+            },
+        };
+
+        let languages = match params.languages {
+            true => get_user(&session.user_id, &mut conn)
+                .await
+                .and_then(|u| u.languages)
+                .unwrap_or_default(),
+            false => Vec::new(),
+        };
+        let post_ids = get_snapshot_page(&snapshot, &languages, offset, limit, &state)
+            .await
+            .ok_or(FuncError::CursorExpired)?;
+
+        let next_cursor = (post_ids.len() as i64 == limit).then(|| {
+            RankCursor {
+                snapshot: snapshot.clone(),
+                offset: offset + limit,
+            }
+            .to_string()
+        });
+        let posts = get_posts_by_ids(&session.user_id, &post_ids, &mut conn, &state).await;
+
+        Ok(response(
+            Page {
+                items: posts,
+                next_cursor,
+            },
+            StatusCode::OK,
+        ))
+    }
+}
+
+/// Recommended posts from followed accounts, popular posts and tags viewer likes
+/// Seen posts are left out, first page builds a new ranking and next ones page through it
+mod for_you {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        database::{
+            popular::{get_current_snapshot, get_snapshot_page},
+            posts::{get_posts_by_ids, get_recommended_ranking},
+            recommendations::{
+                RECOMMENDATIONS_SIZE, get_recommendations_page, store_recommendations,
+            },
+        },
+        utils::pagination::{RankCursor, RankCursorParams},
+    };
+
+    /// Amount of popular posts taken as candidates
+    const POPULAR_CANDIDATES: i64 = 200;
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(cursor): Query<RankCursorParams>,
+    ) -> Result<ApiResponse<Page<Post>>, AppError> {
+        let limit = cursor.limit();
+        let mut conn = get_conn!(state);
+
+        let (snapshot, offset, post_ids) = match cursor.cursor {
+            Some(RankCursor { snapshot, offset }) => {
+                let post_ids =
+                    get_recommendations_page(&session.user_id, &snapshot, offset, limit, &state)
+                        .await
+                        .ok_or(FuncError::CursorExpired)?;
+                (snapshot, offset, post_ids)
+            }
+            None => {
+                let popular_ids = match get_current_snapshot(&state).await {
+                    Some(snapshot) => {
+                        get_snapshot_page(&snapshot, &[], 0, POPULAR_CANDIDATES, &state)
+                            .await
+                            .unwrap_or_default()
+                    }
+                    None => Vec::new(),
+                };
+                let ranking = get_recommended_ranking(
+                    &session.user_id,
+                    &popular_ids,
+                    state.config.popular_gravity,
+                    state.config.popular_window_hours,
+                    RECOMMENDATIONS_SIZE,
+                    &mut conn,
+                )
+                .await;
+
+                let snapshot = Utc::now().timestamp_millis().to_string();
+                store_recommendations(&session.user_id, &snapshot, &ranking, &state).await;
+                let post_ids = ranking
+                    .into_iter()
+                    .take(limit as usize)
+                    .map(|(post_id, _)| post_id)
+                    .collect();
+                (snapshot, 0, post_ids)
+            }
+        };
+
+        let next_cursor = (post_ids.len() as i64 == limit).then(|| {
+            RankCursor {
+                snapshot: snapshot.clone(),
+                offset: offset + limit,
+            }
+            .to_string()
+        });
+        let posts = get_posts_by_ids(&session.user_id, &post_ids, &mut conn, &state).await;
+
+        Ok(response(
+            Page {
+                items: posts,
+                next_cursor,
+            },
+            StatusCode::OK,
+        ))
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new()
+        .route("/home", get(home::handler))
+        .route("/popular", get(popular::handler))
+        .route("/for-you", get(for_you::handler))
+}
@@ -0,0 +1,100 @@
+use axum::{Router, extract::State, http::StatusCode, routing::post};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    create_tx,
+    database::conn::LazyConn,
+    extractors::perms::RequirePerm,
+    get_conn,
+    utils::{
+        response::{ApiResponse, AppError, FuncError, response},
+        state::ArcAppState,
+        validate::ValidatedJson,
+    },
+};
+
+/// Creates file context and gives signed token for uploading its objects to CDN
+mod upload {
+    use crate::{
+        database::files::create_file_context,
+        entities::file::FileType,
+        utils::{
+            perms::Permission,
+            storage::{Operation, generate_signed_token},
+        },
+    };
+
+    use super::*;
+
+    const TOKEN_EXPIRES_SECONDS: u64 = 3600;
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        r#type: FileType,
+        #[validate(range(min = 1))]
+        count: Option<usize>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        pub context_id: String,
+        pub objects: Vec<String>,
+        pub token: String,
+        pub max_size: u64,
+        pub expires_in: u64,
+    }
+
+    pub async fn handler(
+        RequirePerm {
+            session,
+            permissions,
+        }: RequirePerm<0>,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        // Badge icons are only managed from admin panel
+        if payload.r#type == FileType::BadgeIcon && !permissions.contains(Permission::ADMIN_PANEL) {
+            return Err(FuncError::Forbidden.into());
+        }
+        let count = payload.count.unwrap_or(1);
+        if count > payload.r#type.max_count() {
+            return Err(FuncError::IncorrectData.into());
+        }
+
+        let mut conn = get_conn!(state);
+        let mut tx = create_tx!(conn);
+        let context = create_file_context(&session.user_id, payload.r#type, count, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        // Token is scoped to generated objects only
+        let operations: Vec<(Operation, &str)> = context
+            .objects
+            .iter()
+            .map(|object| (Operation::PUT, object.as_str()))
+            .collect();
+        let max_size = payload.r#type.max_size();
+        let token = generate_signed_token(
+            &operations,
+            TOKEN_EXPIRES_SECONDS,
+            Some(max_size),
+            Some(payload.r#type.content_type()),
+            (*state.config).clone(),
+        );
+
+        Ok(response(
+            Returns {
+                context_id: context.context_id,
+                objects: context.objects,
+                token,
+                max_size,
+                expires_in: TOKEN_EXPIRES_SECONDS,
+            },
+            StatusCode::OK,
+        ))
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new().route("/upload", post(upload::handler))
+}
@@ -3,10 +3,28 @@ use axum::Router;
 use crate::utils::state::ArcAppState;
 
 pub mod auth;
+pub mod badges;
+pub mod comments;
+pub mod exports;
+pub mod feed;
+pub mod files;
+pub mod posts;
+pub mod roles;
+pub mod search;
+pub mod tags;
 pub mod users;
 
 pub fn create_router() -> Router<ArcAppState> {
     Router::new()
         .nest("/auth", auth::router())
+        .nest("/badges", badges::router())
+        .nest("/comments", comments::router())
+        .nest("/exports", exports::router())
+        .nest("/feed", feed::router())
+        .nest("/files", files::router())
+        .nest("/posts", posts::router())
+        .nest("/roles", roles::router())
+        .nest("/search", search::router())
+        .nest("/tags", tags::router())
         .nest("/users", users::router())
 }
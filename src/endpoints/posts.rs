@@ -0,0 +1,875 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, patch, post, put},
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::{
+    create_tx,
+    database::{
+        conn::LazyConn,
+        mentions::set_mentions,
+        posts::{PostMeta, get_post, get_post_meta, set_post_tags},
+        users::get_min_user,
+    },
+    entities::post::{POST_FLAGS, POST_TYPE_REPOST, POST_VISIBILITIES, Post},
+    extractors::auth::AuthSession,
+    get_conn,
+    services::publisher::notify_mentions,
+    utils::{
+        response::{ApiResponse, AppError, FuncError, response},
+        state::ArcAppState,
+        text::{extract_hashtags, extract_mentions, merge_tags, normalize_tags},
+        validate::ValidatedJson,
+    },
+};
+
+/// Scheduled posts go live at most a year ahead
+const MAX_SCHEDULE_SECS: i64 = 365 * 86400;
+
+/// Publish time has to be in the future, missing one is valid
+fn validate_publish_at(publish_at: Option<i64>) -> bool {
+    let now = Utc::now().timestamp();
+    publish_at.is_none_or(|at| at > now && at - now <= MAX_SCHEDULE_SECS)
+}
+
+fn validate_flags(flags: &[String]) -> Result<(), ValidationError> {
+    if flags.iter().any(|f| !POST_FLAGS.contains(&f.as_str())) {
+        return Err(ValidationError::new("unknown_flag"));
+    }
+    Ok(())
+}
+
+fn validate_visibility(visibility: &str) -> Result<(), ValidationError> {
+    if !POST_VISIBILITIES.contains(&visibility) {
+        return Err(ValidationError::new("unknown_visibility"));
+    }
+    Ok(())
+}
+
+/// Private function for getting post that is owned by user and not deleted
+async fn get_own_post(
+    post_id: &String,
+    user_id: &String,
+    conn: &mut LazyConn,
+) -> Result<PostMeta, FuncError> {
+    let meta = get_post_meta(post_id, conn)
+        .await
+        .filter(|m| !m.is_deleted)
+        .ok_or(FuncError::PostNotFound)?;
+    if &meta.user_id != user_id {
+        return Err(FuncError::Forbidden);
+    }
+    Ok(meta)
+}
+
+/// Private function for tags of edited post, and whether content or tags change
+/// Hashtags of old content are dropped with it, explicit tags are kept unless replaced
+fn edited_tags(
+    post: &Post,
+    content: Option<&str>,
+    explicit: Option<Vec<String>>,
+) -> (Vec<String>, bool) {
+    let mut old_tags = post.tags.clone().unwrap_or_default();
+    let explicit = explicit.unwrap_or_else(|| {
+        let old_hashtags = extract_hashtags(&post.content);
+        old_tags
+            .iter()
+            .filter(|t| !old_hashtags.contains(t))
+            .cloned()
+            .collect()
+    });
+    let tags = merge_tags(explicit, content.unwrap_or(&post.content));
+
+    old_tags.sort();
+    let mut new_tags = tags.clone();
+    new_tags.sort();
+    let changed = content.is_some_and(|c| c != post.content) || old_tags != new_tags;
+    (tags, changed)
+}
+
+/// Private function for getting original that viewer can repost or quote
+/// Reposts resolve to their original, posts of private accounts can only be shared by author
+async fn get_shareable_post(
+    post_id: &str,
+    user_id: &String,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Result<String, FuncError> {
+    let mut post = get_post(post_id, user_id, conn, state, false)
+        .await
+        .ok_or(FuncError::PostNotFound)?;
+    if post.post_type == POST_TYPE_REPOST {
+        post = *post.reference.ok_or(FuncError::PostNotFound)?;
+    }
+    if &post.user_id != user_id {
+        // Restricted posts can't be shared beyond their audience
+        if !matches!(post.visibility.as_str(), "public" | "unlisted") {
+            return Err(FuncError::Forbidden);
+        }
+        let author = get_min_user(&post.user_id, conn)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+        if author.is_private == Some(true) {
+            return Err(FuncError::Forbidden);
+        }
+    }
+    Ok(post.post_id)
+}
+
+/// Get single post
+mod get_one {
+    use super::*;
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<ApiResponse<Post>, AppError> {
+        let mut conn = get_conn!(state);
+
+        let post = get_post(&post_id, &session.user_id, &mut conn, &state, false)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+        Ok(response(post, StatusCode::OK))
+    }
+}
+
+/// Create new post
+mod create {
+    use super::*;
+    use crate::{
+        database::{
+            files::get_file_context,
+            polls::create_poll,
+            posts::{create_post, set_post_schedule},
+        },
+        entities::{
+            file::FileType,
+            poll::{POLL_MAX_OPTIONS, POLL_MIN_OPTIONS},
+        },
+        services::feed::spawn_fanout,
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[serde(default)]
+        #[validate(length(max = 4000))]
+        content: String,
+        #[validate(length(max = 32))]
+        file_context_id: Option<String>,
+        #[serde(default)]
+        #[validate(length(max = 10))]
+        tags: Vec<String>,
+        #[serde(default)]
+        #[validate(custom(function = "validate_flags"))]
+        flags: Vec<String>,
+        /// Post is created as quote of this one
+        #[validate(length(max = 32))]
+        quote_post_id: Option<String>,
+        #[validate(nested)]
+        poll: Option<PollPayload>,
+        /// Post is saved as draft
+        #[serde(default)]
+        draft: bool,
+        /// Post is scheduled to go live at this time
+        publish_at: Option<i64>,
+        /// Who can see the post, public by default
+        #[validate(custom(function = "validate_visibility"))]
+        visibility: Option<String>,
+    }
+
+    fn validate_poll_options(options: &[String]) -> Result<(), ValidationError> {
+        let mut seen: Vec<&str> = Vec::new();
+        for option in options.iter().map(|o| o.trim()) {
+            if option.is_empty() || option.chars().count() > 100 || seen.contains(&option) {
+                return Err(ValidationError::new("invalid_poll_option"));
+            }
+            seen.push(option);
+        }
+        Ok(())
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct PollPayload {
+        #[validate(
+            length(min = "POLL_MIN_OPTIONS", max = "POLL_MAX_OPTIONS"),
+            custom(function = "validate_poll_options")
+        )]
+        options: Vec<String>,
+        #[serde(default)]
+        multiple_choice: bool,
+        /// Counts are shown only after voting or expiry
+        #[serde(default)]
+        hide_results: bool,
+        /// Seconds until poll closes, from 5 minutes to 30 days
+        #[validate(range(min = 300, max = 2592000))]
+        expires_in: i64,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        pub post_id: String,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        // Post needs either text or media
+        if payload.content.trim().is_empty() && payload.file_context_id.is_none() {
+            return Err(FuncError::IncorrectData.into());
+        }
+        let tags = normalize_tags(&payload.tags).ok_or(FuncError::IncorrectData)?;
+        let tags = merge_tags(tags, &payload.content);
+        let unpublished = payload.draft || payload.publish_at.is_some();
+        if (payload.draft && payload.publish_at.is_some())
+            || !validate_publish_at(payload.publish_at)
+            // Quote counts of the original only follow published quotes
+            || (unpublished && payload.quote_post_id.is_some())
+        {
+            return Err(FuncError::IncorrectData.into());
+        }
+
+        let mut conn = get_conn!(state);
+
+        let quote_post_id = match &payload.quote_post_id {
+            Some(post_id) => {
+                Some(get_shareable_post(post_id, &session.user_id, &mut conn, &state).await?)
+            }
+            None => None,
+        };
+
+        // Media has to be uploaded by author and not attached anywhere yet
+        if let Some(context_id) = &payload.file_context_id {
+            let context = get_file_context(context_id, &mut conn)
+                .await
+                .ok_or(FuncError::InvalidFileContext)?;
+            if !context.is_usable_by(
+                &session.user_id,
+                &[FileType::PostImage, FileType::PostVideo],
+            ) || context.reference_count > 0
+            {
+                return Err(FuncError::InvalidFileContext.into());
+            }
+        }
+
+        let mut tx = create_tx!(conn);
+        let post_id = create_post(
+            &session.user_id,
+            &payload.content,
+            &payload.file_context_id,
+            &payload.flags,
+            &quote_post_id,
+            payload.visibility.as_deref().unwrap_or("public"),
+            &mut tx,
+        )
+        .await;
+        set_post_tags(&post_id, &tags, &mut tx).await;
+        if let Some(poll) = &payload.poll {
+            let options: Vec<String> = poll.options.iter().map(|o| o.trim().to_string()).collect();
+            create_poll(
+                &post_id,
+                &options,
+                poll.multiple_choice,
+                poll.hide_results,
+                poll.expires_in,
+                &mut tx,
+            )
+            .await;
+        }
+        // Unpublished posts mention and fan out once they go live
+        if unpublished {
+            set_post_schedule(&post_id, payload.publish_at, &mut tx).await;
+            tx.commit().await.unwrap();
+            return Ok(response(Returns { post_id }, StatusCode::CREATED));
+        }
+        let mentions = extract_mentions(&payload.content);
+        let mentioned = set_mentions(&session.user_id, &post_id, None, &mentions, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        notify_mentions(&mentioned, &session.user_id, &post_id, &mut conn, &state).await;
+        spawn_fanout(state.clone(), post_id.clone(), session.user_id);
+        Ok(response(Returns { post_id }, StatusCode::CREATED))
+    }
+}
+
+/// Edit own post
+mod patch {
+    use super::*;
+    use crate::database::posts::{create_post_revision, update_post};
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(max = 4000))]
+        content: Option<String>,
+        #[validate(length(max = 10))]
+        tags: Option<Vec<String>>,
+        #[validate(custom(function = "validate_flags"))]
+        flags: Option<Vec<String>>,
+        #[validate(custom(function = "validate_visibility"))]
+        visibility: Option<String>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        let explicit = match &payload.tags {
+            Some(tags) => Some(normalize_tags(tags).ok_or(FuncError::IncorrectData)?),
+            None => None,
+        };
+
+        let mut conn = get_conn!(state);
+        let meta = get_own_post(&post_id, &session.user_id, &mut conn).await?;
+        // Drafts are edited separately, reposts have no content of their own
+        if meta.is_draft {
+            return Err(FuncError::PostNotFound.into());
+        }
+        if meta.post_type == POST_TYPE_REPOST {
+            return Err(FuncError::IncorrectData.into());
+        }
+        if payload
+            .content
+            .as_ref()
+            .is_some_and(|c| c.trim().is_empty() && !meta.has_media)
+        {
+            return Err(FuncError::IncorrectData.into());
+        }
+
+        // Revision is only saved when content, tags or visibility actually change
+        let (tags, changed) = match (&payload.content, explicit, &payload.visibility) {
+            (None, None, None) => (None, false),
+            (content, explicit, visibility) => {
+                let post = get_post(&post_id, &session.user_id, &mut conn, &state, false)
+                    .await
+                    .ok_or(FuncError::PostNotFound)?;
+                let moved = visibility.as_ref().is_some_and(|v| v != &post.visibility);
+                match (content, explicit) {
+                    (None, None) => (None, moved),
+                    (content, explicit) => {
+                        let (tags, changed) = edited_tags(&post, content.as_deref(), explicit);
+                        (Some(tags), changed || moved)
+                    }
+                }
+            }
+        };
+
+        let mut tx = create_tx!(conn);
+        if changed {
+            create_post_revision(&post_id, &mut tx).await;
+        }
+        if !update_post(
+            &post_id,
+            &payload.content,
+            &payload.flags,
+            &payload.visibility,
+            &mut tx,
+        )
+        .await
+        {
+            return Err(FuncError::PostNotFound.into());
+        }
+        if let Some(tags) = tags {
+            set_post_tags(&post_id, &tags, &mut tx).await;
+        }
+        let mentioned = match &payload.content {
+            Some(content) => {
+                let mentions = extract_mentions(content);
+                set_mentions(&session.user_id, &post_id, None, &mentions, &mut tx).await
+            }
+            None => Vec::new(),
+        };
+        tx.commit().await.unwrap();
+
+        notify_mentions(&mentioned, &session.user_id, &post_id, &mut conn, &state).await;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Own drafts and scheduled posts, newest first
+mod drafts {
+    use axum::extract::Query;
+
+    use super::*;
+    use crate::{database::posts::get_drafts, utils::pagination::CursorParams};
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(cursor): Query<CursorParams>,
+    ) -> Result<ApiResponse<Vec<Post>>, AppError> {
+        let mut conn = get_conn!(state);
+        let drafts = get_drafts(&session.user_id, &cursor, &mut conn, &state).await;
+        Ok(response(drafts, StatusCode::OK))
+    }
+}
+
+/// Edit own draft or scheduled post, edits before publishing aren't kept as revisions
+mod edit_draft {
+    use super::*;
+    use crate::database::posts::{get_draft, set_post_schedule, update_post};
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(max = 4000))]
+        content: Option<String>,
+        #[validate(length(max = 10))]
+        tags: Option<Vec<String>>,
+        #[validate(custom(function = "validate_flags"))]
+        flags: Option<Vec<String>>,
+        #[validate(custom(function = "validate_visibility"))]
+        visibility: Option<String>,
+        /// New publish time, null turns scheduled post back into draft
+        #[serde(default, with = "::serde_with::rust::double_option")]
+        publish_at: Option<Option<i64>>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        let explicit = match &payload.tags {
+            Some(tags) => Some(normalize_tags(tags).ok_or(FuncError::IncorrectData)?),
+            None => None,
+        };
+        if !validate_publish_at(payload.publish_at.flatten()) {
+            return Err(FuncError::IncorrectData.into());
+        }
+
+        let mut conn = get_conn!(state);
+        let draft = get_draft(&post_id, &session.user_id, &mut conn, &state)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+        if payload
+            .content
+            .as_ref()
+            .is_some_and(|c| c.trim().is_empty() && draft.media.is_empty())
+        {
+            return Err(FuncError::IncorrectData.into());
+        }
+        let tags = match (&payload.content, explicit) {
+            (None, None) => None,
+            (content, explicit) => Some(edited_tags(&draft, content.as_deref(), explicit).0),
+        };
+
+        let mut tx = create_tx!(conn);
+        if !update_post(
+            &post_id,
+            &payload.content,
+            &payload.flags,
+            &payload.visibility,
+            &mut tx,
+        )
+        .await
+        {
+            return Err(FuncError::PostNotFound.into());
+        }
+        if let Some(tags) = tags {
+            set_post_tags(&post_id, &tags, &mut tx).await;
+        }
+        if let Some(publish_at) = payload.publish_at {
+            set_post_schedule(&post_id, publish_at, &mut tx).await;
+        }
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Publish own draft or scheduled post right away, it keeps its id
+mod publish {
+    use super::*;
+    use crate::{database::posts::get_draft, services::publisher::publish};
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        pub post_id: String,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        let mut conn = get_conn!(state);
+        let draft = get_draft(&post_id, &session.user_id, &mut conn, &state)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+
+        // Publisher may have taken it in the meantime
+        if !publish(
+            &post_id,
+            &session.user_id,
+            &draft.content,
+            &mut conn,
+            &state,
+        )
+        .await
+        {
+            return Err(FuncError::PostNotFound.into());
+        }
+        Ok(response(Returns { post_id }, StatusCode::OK))
+    }
+}
+
+/// Previous versions of post, newest first
+mod revisions {
+    use axum::extract::Query;
+
+    use super::*;
+    use crate::{
+        database::posts::get_post_revisions, entities::post::PostRevision,
+        utils::pagination::CursorParams,
+    };
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+        Query(cursor): Query<CursorParams>,
+    ) -> Result<ApiResponse<Vec<PostRevision>>, AppError> {
+        let mut conn = get_conn!(state);
+        get_post(&post_id, &session.user_id, &mut conn, &state, false)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+
+        let revisions = get_post_revisions(&post_id, &cursor, &mut conn, &state).await;
+        Ok(response(revisions, StatusCode::OK))
+    }
+}
+
+/// Soft delete own post, it can be restored within restore window
+mod delete {
+    use super::*;
+    use crate::database::posts::set_post_deleted;
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        get_own_post(&post_id, &session.user_id, &mut conn).await?;
+
+        let mut tx = create_tx!(conn);
+        set_post_deleted(&post_id, true, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Restore soft deleted post, allowed for author and post moderators
+mod restore {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::{
+        database::posts::set_post_deleted, extractors::perms::RequirePerm, utils::perms::Permission,
+    };
+
+    pub async fn handler(
+        RequirePerm {
+            session,
+            permissions,
+        }: RequirePerm<0>,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let meta = get_post_meta(&post_id, &mut conn)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+
+        if meta.user_id != session.user_id && !permissions.contains(Permission::MODERATE_POSTS) {
+            return Err(FuncError::Forbidden.into());
+        }
+        if !meta.is_deleted {
+            return Ok(StatusCode::NO_CONTENT);
+        }
+
+        let window = state.config.post_restore_days * 86400;
+        if meta
+            .deleted_at
+            .is_some_and(|deleted_at| Utc::now().timestamp() - deleted_at > window)
+        {
+            return Err(FuncError::RestoreWindowExpired.into());
+        }
+
+        let mut tx = create_tx!(conn);
+        set_post_deleted(&post_id, false, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Like or dislike post, or remove own reaction
+mod reaction {
+    use super::*;
+    use crate::{
+        database::reactions::{remove_reaction, set_reaction},
+        entities::reaction::Reaction,
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        reaction: Reaction,
+    }
+
+    pub async fn put(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        get_post(&post_id, &session.user_id, &mut conn, &state, false)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+
+        let mut tx = create_tx!(conn);
+        set_reaction(&session.user_id, &post_id, None, payload.reaction, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn delete(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        let mut tx = create_tx!(conn);
+        remove_reaction(&session.user_id, &post_id, None, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Save post to favorites or remove it from them
+mod favorite {
+    use super::*;
+    use crate::database::favorites::{add_favorite, remove_favorite};
+
+    pub async fn put(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        get_post(&post_id, &session.user_id, &mut conn, &state, false)
+            .await
+            .ok_or(FuncError::PostNotFound)?;
+
+        let mut tx = create_tx!(conn);
+        add_favorite(&session.user_id, &post_id, None, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn delete(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        let mut tx = create_tx!(conn);
+        remove_favorite(&session.user_id, &post_id, None, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Vote in poll of post, vote can't be changed
+mod vote {
+    use super::*;
+    use crate::{
+        database::polls::vote_poll,
+        entities::poll::{POLL_MAX_OPTIONS, Poll},
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        /// Positions of chosen options
+        #[validate(length(min = 1, max = "POLL_MAX_OPTIONS"))]
+        options: Vec<i16>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+        ValidatedJson(mut payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<Poll>, AppError> {
+        payload.options.sort();
+        payload.options.dedup();
+
+        let mut conn = get_conn!(state);
+        let poll = get_post(&post_id, &session.user_id, &mut conn, &state, false)
+            .await
+            .ok_or(FuncError::PostNotFound)?
+            .poll
+            .ok_or(FuncError::PollNotFound)?;
+        if poll.closed {
+            return Err(FuncError::PollClosed.into());
+        }
+        if poll.own_vote.is_some() {
+            return Err(FuncError::AlreadyVoted.into());
+        }
+        if (!poll.multiple_choice && payload.options.len() > 1)
+            || payload
+                .options
+                .iter()
+                .any(|&o| o < 0 || o as usize >= poll.options.len())
+        {
+            return Err(FuncError::IncorrectData.into());
+        }
+
+        let mut tx = create_tx!(conn);
+        if !vote_poll(&post_id, &session.user_id, &payload.options, &mut tx).await {
+            return Err(FuncError::AlreadyVoted.into());
+        }
+        tx.commit().await.unwrap();
+
+        let poll = get_post(&post_id, &session.user_id, &mut conn, &state, false)
+            .await
+            .and_then(|p| p.poll)
+            .ok_or(FuncError::PollNotFound)?;
+        Ok(response(poll, StatusCode::OK))
+    }
+}
+
+/// Repost post or remove own repost, reposting a repost reposts its original
+mod repost {
+    use super::*;
+    use crate::{
+        database::posts::{create_repost, delete_repost},
+        services::feed::spawn_fanout,
+    };
+
+    pub async fn put(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let original = get_shareable_post(&post_id, &session.user_id, &mut conn, &state).await?;
+
+        let mut tx = create_tx!(conn);
+        let repost_id = create_repost(&session.user_id, &original, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        if let Some(repost_id) = repost_id {
+            spawn_fanout(state.clone(), repost_id, session.user_id);
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn delete(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(post_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        let mut tx = create_tx!(conn);
+        delete_repost(&session.user_id, &post_id, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Record posts that client displayed, they are left out of "for you" feed
+/// Views are buffered and stored in bulk, unknown posts are skipped then
+mod views {
+    use chrono::Utc;
+
+    use super::*;
+    use crate::database::views::{PostView, buffer_views, insert_views};
+
+    fn validate_post_ids(post_ids: &[String]) -> Result<(), ValidationError> {
+        if post_ids.iter().any(|id| id.parse::<i64>().is_err()) {
+            return Err(ValidationError::new("invalid_post_id"));
+        }
+        Ok(())
+    }
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(min = 1, max = 100), custom(function = "validate_post_ids"))]
+        post_ids: Vec<String>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(mut payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        payload.post_ids.sort();
+        payload.post_ids.dedup();
+        let timestamp = Utc::now().timestamp();
+
+        // Without Redis views are written directly
+        if !buffer_views(&session.user_id, &payload.post_ids, timestamp, &state).await {
+            let views: Vec<PostView> = payload
+                .post_ids
+                .into_iter()
+                .map(|post_id| PostView {
+                    user_id: session.user_id.clone(),
+                    post_id,
+                    timestamp,
+                })
+                .collect();
+            let mut conn = get_conn!(state);
+            insert_views(&views, &mut conn).await;
+        }
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new()
+        .route("/", post(create::handler))
+        .route("/views", post(views::handler))
+        .route("/drafts", get(drafts::handler))
+        .route("/drafts/{post_id}", patch(edit_draft::handler))
+        .route("/drafts/{post_id}/publish", post(publish::handler))
+        .route(
+            "/{post_id}",
+            get(get_one::handler)
+                .patch(patch::handler)
+                .delete(delete::handler),
+        )
+        .route("/{post_id}/restore", post(restore::handler))
+        .route("/{post_id}/revisions", get(revisions::handler))
+        .route(
+            "/{post_id}/reaction",
+            put(reaction::put).delete(reaction::delete),
+        )
+        .route(
+            "/{post_id}/favorite",
+            put(favorite::put).delete(favorite::delete),
+        )
+        .route("/{post_id}/poll", put(vote::handler))
+        .route("/{post_id}/repost", put(repost::put).delete(repost::delete))
+}
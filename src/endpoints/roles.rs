@@ -0,0 +1,294 @@
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, put},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::{
+    create_tx,
+    database::{
+        conn::LazyConn,
+        roles::{get_role_rank, get_user_rank, invalidate_role_cache},
+    },
+    extractors::perms::RequirePerm,
+    get_conn,
+    utils::{
+        perms::{Permission, permission_from_name},
+        response::{ApiResponse, AppError, FuncError, response},
+        state::ArcAppState,
+        validate::ValidatedJson,
+    },
+};
+
+type AdminSession = RequirePerm<{ Permission::ADMIN_PANEL.bits() }>;
+
+/// Private function for getting rank of admin's role
+/// Admins only manage roles ranked below their own, so nobody can edit owner or raise themselves
+async fn get_admin_rank(
+    admin: &AdminSession,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> Result<i32, FuncError> {
+    get_user_rank(&admin.session.user_id, conn, state)
+        .await
+        .ok_or(FuncError::UserNotFound)
+}
+
+/// List of all roles with permissions
+mod list {
+    use super::*;
+    use crate::{database::roles::get_roles, entities::role::Role};
+
+    pub async fn handler(
+        _admin: AdminSession,
+        State(state): State<ArcAppState>,
+    ) -> Result<ApiResponse<Vec<Role>>, AppError> {
+        let mut conn = get_conn!(state);
+
+        let roles = get_roles(&mut conn).await;
+        Ok(response(roles, StatusCode::OK))
+    }
+}
+
+/// Create new role
+mod create {
+    use super::*;
+    use crate::database::roles::{create_role, role_name_exists};
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(min = 1, max = 32))]
+        name: String,
+        #[serde(default)]
+        permissions: Vec<String>,
+        /// Defaults to right below admin's own rank
+        #[validate(range(min = 0))]
+        rank: Option<i32>,
+    }
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        pub role_id: i32,
+    }
+
+    pub async fn handler(
+        admin: AdminSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        let mut conn = get_conn!(state);
+
+        let mut permissions = Permission::NONE;
+        for name in &payload.permissions {
+            permissions |= permission_from_name(name).ok_or(FuncError::IncorrectData)?;
+        }
+        // Admins can't hand out permissions they don't have or create roles they can't manage
+        let admin_rank = get_admin_rank(&admin, &mut conn, &state).await?;
+        let rank = payload.rank.unwrap_or(admin_rank - 1);
+        if !admin.permissions.contains(permissions) || rank >= admin_rank {
+            return Err(FuncError::Forbidden.into());
+        }
+
+        if role_name_exists(&payload.name, &mut conn).await {
+            return Err(FuncError::RoleExists.into());
+        }
+
+        let mut tx = create_tx!(conn);
+        let role_id = create_role(&payload.name, rank, permissions, &mut tx)
+            .await
+            .ok_or(FuncError::RoleExists)?;
+        tx.commit().await.unwrap();
+
+        Ok(response(Returns { role_id }, StatusCode::CREATED))
+    }
+}
+
+/// Enable or disable single permission of role
+mod toggle_permission {
+    use super::*;
+    use crate::database::roles::set_role_permission;
+
+    async fn toggle(
+        enabled: bool,
+        admin: AdminSession,
+        state: ArcAppState,
+        role_id: i32,
+        permission: String,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        let flag = permission_from_name(&permission).ok_or(FuncError::IncorrectData)?;
+        let rank = get_role_rank(&role_id, &mut conn)
+            .await
+            .ok_or(FuncError::RoleNotFound)?;
+        if rank >= get_admin_rank(&admin, &mut conn, &state).await?
+            || (enabled && !admin.permissions.contains(flag))
+        {
+            return Err(FuncError::Forbidden.into());
+        }
+
+        let mut tx = create_tx!(conn);
+        let changed = set_role_permission(&role_id, &permission, enabled, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        if changed {
+            invalidate_role_cache(&role_id, &state).await;
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn enable(
+        admin: AdminSession,
+        State(state): State<ArcAppState>,
+        Path((role_id, permission)): Path<(i32, String)>,
+    ) -> Result<StatusCode, AppError> {
+        toggle(true, admin, state, role_id, permission).await
+    }
+
+    pub async fn disable(
+        admin: AdminSession,
+        State(state): State<ArcAppState>,
+        Path((role_id, permission)): Path<(i32, String)>,
+    ) -> Result<StatusCode, AppError> {
+        toggle(false, admin, state, role_id, permission).await
+    }
+}
+
+/// Assign role to user, admins can only move users below their own rank
+mod assign_role {
+    use super::*;
+    use crate::database::roles::{invalidate_user_role_cache, set_user_role};
+
+    pub async fn handler(
+        admin: AdminSession,
+        State(state): State<ArcAppState>,
+        Path((role_id, user_id)): Path<(i32, String)>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        let rank = get_role_rank(&role_id, &mut conn)
+            .await
+            .ok_or(FuncError::RoleNotFound)?;
+        let current = get_user_rank(&user_id, &mut conn, &state)
+            .await
+            .ok_or(FuncError::UserNotFound)?;
+        let admin_rank = get_admin_rank(&admin, &mut conn, &state).await?;
+        if rank >= admin_rank || current >= admin_rank {
+            return Err(FuncError::Forbidden.into());
+        }
+
+        let mut tx = create_tx!(conn);
+        let changed = set_user_role(&user_id, &role_id, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        if changed {
+            invalidate_user_role_cache(&user_id, &state).await;
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new()
+        .route("/", get(list::handler).post(create::handler))
+        .route(
+            "/{role_id}/permissions/{permission}",
+            put(toggle_permission::enable).delete(toggle_permission::disable),
+        )
+        .route("/{role_id}/users/{user_id}", put(assign_role::handler))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        database::{
+            roles::{get_roles, get_user_role_id, invalidate_user_role_cache, set_user_role},
+            test_support::{cleanup, new_user},
+        },
+        extractors::auth::AuthSession,
+        utils::{state::AppState, thread_state::generate_id},
+    };
+
+    fn session(user_id: &str, permissions: Permission) -> AdminSession {
+        RequirePerm {
+            session: AuthSession {
+                user_id: user_id.to_string(),
+                session_id: String::new(),
+            },
+            permissions,
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn admin_manages_roles_they_created() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        let mut conn = get_conn!(state);
+        let admin_id = new_user(&mut conn).await;
+        let user_id = new_user(&mut conn).await;
+        let mut tx = create_tx!(conn);
+        set_user_role(&admin_id, &4, &mut tx).await;
+        tx.commit().await.unwrap();
+        invalidate_user_role_cache(&admin_id, &state).await;
+        let admin = || session(&admin_id, Permission::all());
+
+        let name = format!("tier_{}", generate_id());
+        let payload = serde_json::from_value(json!({ "name": name })).unwrap();
+        create::handler(admin(), State(state.clone()), ValidatedJson(payload))
+            .await
+            .unwrap();
+        let role = get_roles(&mut conn)
+            .await
+            .into_iter()
+            .find(|r| r.name == name)
+            .unwrap();
+        // New tier goes between moderator and admin, whatever its id is
+        assert_eq!(role.rank, 399);
+
+        toggle_permission::enable(
+            admin(),
+            State(state.clone()),
+            Path((role.role_id, "BAN_USERS".to_string())),
+        )
+        .await
+        .unwrap();
+        assign_role::handler(
+            admin(),
+            State(state.clone()),
+            Path((role.role_id, user_id.clone())),
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            get_user_role_id(&user_id, &mut conn, &state).await,
+            Some(role.role_id)
+        );
+
+        // Roles of admin's rank and above are out of reach
+        let payload =
+            serde_json::from_value(json!({ "name": format!("{}_top", name), "rank": 400 }))
+                .unwrap();
+        let created = create::handler(admin(), State(state.clone()), ValidatedJson(payload)).await;
+        assert!(matches!(created, Err(AppError::Forbidden(_))));
+        let toggled =
+            toggle_permission::enable(admin(), State(state.clone()), Path((4, "BAN_USERS".into())))
+                .await;
+        assert!(matches!(toggled, Err(AppError::Forbidden(_))));
+
+        invalidate_user_role_cache(&user_id, &state).await;
+        invalidate_role_cache(&role.role_id, &state).await;
+        cleanup(&[&admin_id, &user_id], &mut conn).await;
+        let db = conn.get_client().await.unwrap();
+        db.execute("DELETE FROM roles WHERE role_id = $1", &[&role.role_id])
+            .await
+            .unwrap();
+    }
+}
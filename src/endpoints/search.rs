@@ -0,0 +1,110 @@
+use axum::{
+    Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+};
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::{
+    database::conn::LazyConn,
+    extractors::auth::AuthSession,
+    get_conn,
+    utils::{
+        pagination::Page,
+        response::{ApiResponse, AppError, FuncError, response},
+        state::ArcAppState,
+    },
+};
+
+/// Full-text search of posts, best matches first
+/// Supports "phrases", prefix* terms, OR and -exclusions
+/// Query language defaults to viewer's first language
+mod posts {
+    use super::*;
+    use crate::{
+        database::{
+            posts::{PostSearch, search_posts},
+            users::get_user,
+        },
+        entities::post::Post,
+        utils::text::{normalize_tag, split_search_query},
+    };
+
+    const DEFAULT_LIMIT: i64 = 20;
+    const MAX_LIMIT: i64 = 50;
+    /// Deep pages are expensive to rank, refining the query is expected instead
+    const MAX_OFFSET: i64 = 1000;
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Params {
+        #[validate(length(min = 1, max = 256))]
+        q: String,
+        #[validate(length(max = 16))]
+        lang: Option<String>,
+        author: Option<String>,
+        tag: Option<String>,
+        since: Option<i64>,
+        until: Option<i64>,
+        has_media: Option<bool>,
+        #[validate(range(min = 0, max = "MAX_OFFSET"))]
+        cursor: Option<i64>,
+        limit: Option<i64>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(params): Query<Params>,
+    ) -> Result<ApiResponse<Page<Post>>, AppError> {
+        params.validate().map_err(|_| FuncError::IncorrectData)?;
+        let (text, prefixes) = split_search_query(&params.q);
+        if text.trim().is_empty() && prefixes.is_empty() {
+            return Err(FuncError::IncorrectData.into());
+        }
+        let tag = match &params.tag {
+            Some(tag) => Some(normalize_tag(tag).ok_or(FuncError::IncorrectData)?),
+            None => None,
+        };
+
+        let mut conn = get_conn!(state);
+        let language = match params.lang {
+            Some(lang) => Some(lang),
+            None => get_user(&session.user_id, &mut conn)
+                .await
+                .and_then(|u| u.languages)
+                .and_then(|l| l.into_iter().next()),
+        };
+
+        let search = PostSearch {
+            text,
+            prefixes,
+            language,
+            author_id: params.author,
+            tag,
+            since: params.since,
+            until: params.until,
+            has_media: params.has_media,
+            offset: params.cursor.unwrap_or(0),
+            limit: params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT),
+        };
+        let posts = search_posts(&session.user_id, &search, &mut conn, &state).await;
+
+        let next_offset = search.offset + search.limit;
+        let next_cursor = (posts.len() as i64 == search.limit && next_offset <= MAX_OFFSET)
+            .then(|| next_offset.to_string());
+
+        Ok(response(
+            Page {
+                items: posts,
+                next_cursor,
+            },
+            StatusCode::OK,
+        ))
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new().route("/posts", get(posts::handler))
+}
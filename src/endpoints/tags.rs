@@ -0,0 +1,131 @@
+use axum::{
+    Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::conn::LazyConn,
+    extractors::auth::AuthSession,
+    get_conn,
+    utils::{
+        response::{ApiResponse, AppError, FuncError, response},
+        state::ArcAppState,
+        text::normalize_tag,
+    },
+};
+
+/// Tag with page of its posts, newest first, paginated like user posts
+mod get_one {
+    use axum::extract::Path;
+
+    use super::*;
+    use crate::{
+        database::{posts::get_tag_posts, tags::get_tag},
+        entities::{post::Post, tag::Tag},
+        utils::pagination::CursorParams,
+    };
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        pub tag: Tag,
+        pub posts: Vec<Post>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(name): Path<String>,
+        Query(cursor): Query<CursorParams>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        let name = normalize_tag(&name).ok_or(FuncError::TagNotFound)?;
+        let mut conn = get_conn!(state);
+        let tag = get_tag(&name, &mut conn)
+            .await
+            .ok_or(FuncError::TagNotFound)?;
+
+        let posts = get_tag_posts(&tag.tag_id, &session.user_id, &cursor, &mut conn, &state).await;
+        Ok(response(Returns { tag, posts }, StatusCode::OK))
+    }
+}
+
+/// Tags starting with prefix for autocomplete, most used first
+mod search {
+    use super::*;
+    use crate::{database::tags::search_tags, entities::tag::Tag};
+
+    const DEFAULT_LIMIT: i64 = 10;
+    const MAX_LIMIT: i64 = 20;
+
+    #[derive(Debug, Deserialize)]
+    pub struct Params {
+        prefix: String,
+        limit: Option<i64>,
+    }
+
+    pub async fn handler(
+        _session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(params): Query<Params>,
+    ) -> Result<ApiResponse<Vec<Tag>>, AppError> {
+        // Prefix that can't start a tag matches nothing
+        let Some(prefix) = normalize_tag(&params.prefix) else {
+            return Ok(response(Vec::new(), StatusCode::OK));
+        };
+        let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+        let mut conn = get_conn!(state);
+        let tags = search_tags(&prefix, limit, &mut conn).await;
+        Ok(response(tags, StatusCode::OK))
+    }
+}
+
+/// Tags growing fastest compared to the previous window of the same length
+mod trending {
+    use super::*;
+    use crate::{database::tags::get_trending_tags, entities::tag::TrendingTag};
+
+    #[derive(Debug, Deserialize, Default, Clone, Copy)]
+    #[serde(rename_all = "snake_case")]
+    pub enum Window {
+        Hour,
+        #[default]
+        Day,
+        Week,
+    }
+
+    impl Window {
+        fn hours(self) -> i64 {
+            match self {
+                Window::Hour => 1,
+                Window::Day => 24,
+                Window::Week => 168,
+            }
+        }
+    }
+
+    #[derive(Debug, Deserialize)]
+    pub struct Params {
+        #[serde(default)]
+        window: Window,
+    }
+
+    pub async fn handler(
+        _session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(params): Query<Params>,
+    ) -> Result<ApiResponse<Vec<TrendingTag>>, AppError> {
+        let mut conn = get_conn!(state);
+        let tags = get_trending_tags(params.window.hours(), &mut conn, &state).await;
+        Ok(response(tags, StatusCode::OK))
+    }
+}
+
+pub fn router() -> Router<ArcAppState> {
+    Router::new()
+        .route("/", get(search::handler))
+        .route("/trending", get(trending::handler))
+        .route("/{name}", get(get_one::handler))
+}
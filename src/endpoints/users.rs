@@ -1,10 +1,23 @@
-use axum::{Router, extract::State, http::StatusCode, routing::get};
-use serde::Serialize;
+use axum::{
+    Router,
+    extract::State,
+    http::StatusCode,
+    routing::{get, post, put},
+};
+use serde::{Deserialize, Serialize};
 
 use crate::{
     create_tx,
-    database::conn::LazyConn,
-    entities::user::User,
+    database::{
+        badges::get_badges_by_ids,
+        conn::LazyConn,
+        follows::is_following,
+        presence::{Presence, get_presences},
+        settings::get_user_settings,
+        timelines::invalidate_timeline,
+        users::{get_min_user, get_user},
+    },
+    entities::{badge::Badge, user::User},
     extractors::auth::AuthSession,
     get_conn,
     utils::{
@@ -13,12 +26,69 @@ use crate::{
     },
 };
 
+/// Get user as seen by viewer
+/// Private accounts are reduced to minimized user for everyone except approved followers
+/// Returns: (user, is_reduced)
+async fn get_user_for_viewer(
+    viewer_id: &String,
+    user_id: &String,
+    conn: &mut LazyConn,
+) -> Option<(User, bool)> {
+    let user = get_user(user_id, conn).await?;
+    if user.is_private == Some(true)
+        && viewer_id != user_id
+        && !is_following(viewer_id, user_id, conn).await
+    {
+        return get_min_user(user_id, conn).await.map(|u| (u, true));
+    }
+    Some((user, false))
+}
+
+/// Query for including related objects in profile, e.g. ?expand=badges
+#[derive(Debug, Deserialize)]
+pub struct ExpandParams {
+    expand: Option<String>,
+}
+
+impl ExpandParams {
+    fn has(&self, field: &str) -> bool {
+        self.expand
+            .as_deref()
+            .is_some_and(|e| e.split(',').any(|f| f.trim() == field))
+    }
+}
+
+/// Replaces badge ids of user with badge objects if they were requested
+async fn expand_badges(
+    user: &mut User,
+    params: &ExpandParams,
+    conn: &mut LazyConn,
+) -> Option<Vec<Badge>> {
+    if !params.has("badges") {
+        return None;
+    }
+    let ids = user.badges.take().unwrap_or_default();
+    Some(get_badges_by_ids(&ids, conn).await)
+}
+
+/// Check if user shares their presence with viewer
+async fn shows_presence(
+    viewer_id: &String,
+    user_id: &String,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> bool {
+    viewer_id == user_id
+        || get_user_settings(user_id, conn, state)
+            .await
+            .privacy
+            .show_presence
+}
+
 mod me {
+    use axum::extract::Query;
 
-    use crate::{
-        database::users::get_user,
-        utils::perms::{permissions_to_list, role_permissions},
-    };
+    use crate::{database::roles::get_role_permissions, utils::perms::permissions_to_list};
 
     use super::*;
 
@@ -29,18 +99,22 @@ mod me {
         pub user: User,
         pub created_at: f64,
         pub permissions: Vec<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub badges: Option<Vec<Badge>>,
     }
 
     pub async fn handler(
         session: AuthSession,
         State(state): State<ArcAppState>,
+        Query(params): Query<ExpandParams>,
     ) -> Result<ApiResponse<Returns>, AppError> {
         let mut conn = get_conn!(state);
-        let user = get_user(&session.user_id, &mut conn)
+        let mut user = get_user(&session.user_id, &mut conn)
             .await
             .ok_or(FuncError::UserNotFound)?;
+        let badges = expand_badges(&mut user, &params, &mut conn).await;
 
-        let perms = role_permissions(&user.role_id);
+        let perms = get_role_permissions(&user.role_id, &mut conn, &state).await;
         let permissions = permissions_to_list(perms);
 
         Ok(response(
@@ -48,6 +122,7 @@ mod me {
                 created_at: user.created_at(),
                 user,
                 permissions,
+                badges,
             },
             StatusCode::OK,
         ))
@@ -60,11 +135,37 @@ mod patch_me {
 
     use super::*;
     use crate::{
-        database::users::{UserProfileUpdate, update_user_profile},
+        database::{
+            files::get_file_context,
+            follows::approve_follow_requests,
+            notifications::create_notifications,
+            settings::get_users_settings,
+            users::{UserProfileUpdate, update_user_profile},
+        },
+        entities::file::FileType,
         map_struct,
         utils::validate::ValidatedJson,
     };
 
+    /// Private function for checking that file context belongs to user and has correct type
+    async fn check_file_context(
+        context_id: &Option<String>,
+        user_id: &str,
+        r#type: FileType,
+        conn: &mut LazyConn,
+    ) -> Result<(), FuncError> {
+        let Some(context_id) = context_id else {
+            return Ok(());
+        };
+        let context = get_file_context(context_id, conn)
+            .await
+            .ok_or(FuncError::InvalidFileContext)?;
+        if !context.is_usable_by(user_id, &[r#type]) {
+            return Err(FuncError::InvalidFileContext);
+        }
+        Ok(())
+    }
+
     fn validate_languages(langs: &Vec<String>) -> Result<(), ValidationError> {
         if langs.len() > 8 {
             return Err(ValidationError::new("too_many_languages"));
@@ -90,6 +191,7 @@ mod patch_me {
         pub bio: Option<String>,
         #[validate(custom(function = "validate_languages"))]
         pub languages: Option<Vec<String>>,
+        pub is_private: Option<bool>,
     }
 
     pub async fn handler(
@@ -98,7 +200,23 @@ mod patch_me {
         ValidatedJson(payload): ValidatedJson<Payload>,
     ) -> Result<StatusCode, AppError> {
         let mut conn = get_conn!(state);
+        check_file_context(
+            &payload.avatar_context_id,
+            &session.user_id,
+            FileType::Avatar,
+            &mut conn,
+        )
+        .await?;
+        check_file_context(
+            &payload.banner_context_id,
+            &session.user_id,
+            FileType::Banner,
+            &mut conn,
+        )
+        .await?;
+
         let mut tx = create_tx!(conn);
+        let made_public = payload.is_private == Some(false);
 
         // We convert PatchPayload to UserProfileUpdate so we can validate
         // Validation has to be in endpoints/ not in database/ so we gotta do this here
@@ -113,23 +231,45 @@ mod patch_me {
                 banner_context_id,
                 bio,
                 languages,
+                is_private,
             }),
             &mut tx,
         )
         .await;
 
+        // Public accounts have no follow requests, pending ones are approved
+        let mut approved = Vec::new();
+        if made_public {
+            approved = approve_follow_requests(&session.user_id, &mut tx).await;
+            let recipients: Vec<String> = get_users_settings(&approved, &mut tx)
+                .await
+                .into_iter()
+                .filter(|(_, settings)| settings.notifications.allows("follow_accepted"))
+                .map(|(user_id, _)| user_id)
+                .collect();
+            create_notifications(
+                &recipients,
+                &session.user_id,
+                "follow_accepted",
+                None,
+                &mut tx,
+            )
+            .await;
+        }
+
         if dirty {
             tx.commit().await.unwrap();
         }
+        for user_id in &approved {
+            invalidate_timeline(user_id, &state).await;
+        }
 
         Ok(StatusCode::NO_CONTENT)
     }
 }
 
 mod get_user {
-    use axum::extract::Path;
-
-    use crate::database::users::get_user;
+    use axum::extract::{Path, Query};
 
     use super::*;
 
@@ -138,30 +278,613 @@ mod get_user {
         #[serde(flatten)]
         pub user: User,
         pub created_at: f64,
+        #[serde(flatten)]
+        pub presence: Option<Presence>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub badges: Option<Vec<Badge>>,
     }
 
     pub async fn handler(
-        _session: AuthSession,
+        session: AuthSession,
         State(state): State<ArcAppState>,
         Path(user_id): Path<String>,
+        Query(params): Query<ExpandParams>,
     ) -> Result<ApiResponse<Returns>, AppError> {
         let mut conn = get_conn!(state);
-        let user = get_user(&user_id, &mut conn)
+        let (mut user, reduced) = get_user_for_viewer(&session.user_id, &user_id, &mut conn)
             .await
             .ok_or(FuncError::UserNotFound)?;
+        let badges = expand_badges(&mut user, &params, &mut conn).await;
+
+        let presence =
+            if !reduced && shows_presence(&session.user_id, &user_id, &mut conn, &state).await {
+                get_presences(&[user_id], &state).await.pop()
+            } else {
+                None
+            };
 
         Ok(response(
             Returns {
                 created_at: user.created_at(),
                 user,
+                presence,
+                badges,
             },
             StatusCode::OK,
         ))
     }
 }
 
+/// Posts of user, paginated by post id
+mod user_posts {
+    use axum::extract::{Path, Query};
+
+    use super::*;
+    use crate::{
+        database::posts::get_user_posts, entities::post::Post, utils::pagination::CursorParams,
+    };
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+        Query(cursor): Query<CursorParams>,
+    ) -> Result<ApiResponse<Vec<Post>>, AppError> {
+        let mut conn = get_conn!(state);
+        if get_min_user(&user_id, &mut conn).await.is_none() {
+            return Err(FuncError::UserNotFound.into());
+        }
+
+        let posts = get_user_posts(&user_id, &session.user_id, &cursor, &mut conn, &state).await;
+        Ok(response(posts, StatusCode::OK))
+    }
+}
+
+/// Posts saved to favorites by current user, most recently saved first
+mod favorites {
+    use axum::extract::Query;
+
+    use super::*;
+    use crate::{
+        database::posts::get_favorite_posts,
+        entities::post::Post,
+        utils::pagination::{Page, TimeCursorParams},
+    };
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Query(cursor): Query<TimeCursorParams>,
+    ) -> Result<ApiResponse<Page<Post>>, AppError> {
+        let mut conn = get_conn!(state);
+
+        let posts = get_favorite_posts(&session.user_id, &cursor, &mut conn, &state).await;
+        let next_cursor = match posts.last() {
+            Some((last, _)) if posts.len() as i64 == cursor.limit() => Some(last.to_string()),
+            _ => None,
+        };
+        let items = posts.into_iter().map(|(_, post)| post).collect();
+
+        Ok(response(Page { items, next_cursor }, StatusCode::OK))
+    }
+}
+
+mod change_username {
+    use chrono::Utc;
+    use serde::Deserialize;
+    use validator::Validate;
+
+    use super::*;
+    use crate::{
+        database::{
+            auth::username_exists,
+            users::{change_username, lock_username, resolve_username},
+        },
+        utils::validate::{ValidatedJson, validate_username},
+    };
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(min = 1, max = 32), custom(function = "validate_username"))]
+        username: String,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        // User can take back their own reserved username
+        if username_exists(&payload.username, &mut conn).await {
+            let owner = resolve_username(&payload.username, &mut conn).await;
+            if owner.is_none_or(|(user_id, _)| user_id != session.user_id) {
+                return Err(FuncError::UsernameExists.into());
+            }
+        }
+
+        // Checks are done on locked row, so concurrent renames can't skip cooldown
+        let mut tx = create_tx!(conn);
+        let (username, changed_at) = lock_username(&session.user_id, &mut tx)
+            .await
+            .ok_or(FuncError::UserNotFound)?;
+        if username == payload.username {
+            return Ok(StatusCode::NO_CONTENT);
+        }
+        if let Some(changed_at) = changed_at {
+            let cooldown = state.config.username_change_cooldown_days * 24 * 3600;
+            if Utc::now().timestamp() - changed_at < cooldown {
+                return Err(FuncError::UsernameChangeCooldown.into());
+            }
+        }
+
+        // Username could be taken by someone else since the check
+        if !change_username(
+            &session.user_id,
+            &username,
+            &payload.username,
+            state.config.username_reserve_days,
+            &mut tx,
+        )
+        .await
+        {
+            return Err(FuncError::UsernameExists.into());
+        }
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+mod get_by_username {
+    use axum::extract::{Path, Query};
+
+    use crate::database::users::resolve_username;
+
+    use super::*;
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        #[serde(flatten)]
+        pub user: User,
+        pub created_at: f64,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub redirected_from: Option<String>,
+        #[serde(flatten)]
+        pub presence: Option<Presence>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        pub badges: Option<Vec<Badge>>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(username): Path<String>,
+        Query(params): Query<ExpandParams>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        let mut conn = get_conn!(state);
+
+        // Recently used old usernames resolve to the current account
+        let (user_id, is_old) = resolve_username(&username, &mut conn)
+            .await
+            .ok_or(FuncError::UserNotFound)?;
+        let (mut user, reduced) = get_user_for_viewer(&session.user_id, &user_id, &mut conn)
+            .await
+            .ok_or(FuncError::UserNotFound)?;
+        let badges = expand_badges(&mut user, &params, &mut conn).await;
+
+        let presence =
+            if !reduced && shows_presence(&session.user_id, &user_id, &mut conn, &state).await {
+                get_presences(&[user_id], &state).await.pop()
+            } else {
+                None
+            };
+
+        Ok(response(
+            Returns {
+                created_at: user.created_at(),
+                user,
+                redirected_from: is_old.then_some(username),
+                presence,
+                badges,
+            },
+            StatusCode::OK,
+        ))
+    }
+}
+
+mod settings {
+    use super::*;
+    use crate::{database::settings::get_user_settings, entities::settings::UserSettings};
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+    ) -> Result<ApiResponse<UserSettings>, AppError> {
+        let mut conn = get_conn!(state);
+        let settings = get_user_settings(&session.user_id, &mut conn, &state).await;
+
+        Ok(response(settings, StatusCode::OK))
+    }
+}
+
+mod patch_settings {
+    use serde::Deserialize;
+    use serde_json::{Map, Value};
+    use validator::{Validate, ValidationErrors};
+
+    use super::*;
+    use crate::{
+        database::settings::{get_user_settings, invalidate_settings_cache, update_user_settings},
+        entities::settings::UserSettings,
+        utils::{json::merge_patch, validate::ValidatedJson},
+    };
+
+    /// Partial settings, merged into current ones as JSON merge patch
+    /// Setting a field to null resets it to default
+    #[derive(Debug, Deserialize)]
+    pub struct Payload(Map<String, Value>);
+
+    impl Validate for Payload {
+        fn validate(&self) -> Result<(), ValidationErrors> {
+            // Actual validation happens on merged UserSettings
+            Ok(())
+        }
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<UserSettings>, AppError> {
+        let mut conn = get_conn!(state);
+        let current = get_user_settings(&session.user_id, &mut conn, &state).await;
+
+        let mut merged = serde_json::to_value(&current).unwrap();
+        merge_patch(&mut merged, Value::Object(payload.0));
+
+        let settings: UserSettings =
+            serde_json::from_value(merged).map_err(|_| FuncError::IncorrectData)?;
+        settings.validate().map_err(|_| FuncError::IncorrectData)?;
+
+        let mut tx = create_tx!(conn);
+        update_user_settings(&session.user_id, &settings, &mut tx).await;
+        tx.commit().await.unwrap();
+        invalidate_settings_cache(&session.user_id, &state).await;
+
+        Ok(response(settings, StatusCode::OK))
+    }
+}
+
+mod heartbeat {
+    use super::*;
+
+    /// Presence itself is updated by AuthSession, so this only keeps user online
+    pub async fn handler(_session: AuthSession) -> StatusCode {
+        StatusCode::NO_CONTENT
+    }
+}
+
+mod bulk_presence {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+    use validator::Validate;
+
+    use super::*;
+    use crate::{database::settings::get_visible_users_settings, utils::validate::ValidatedJson};
+
+    #[derive(Debug, Deserialize, Validate)]
+    pub struct Payload {
+        #[validate(length(min = 1, max = 100))]
+        user_ids: Vec<String>,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        ValidatedJson(payload): ValidatedJson<Payload>,
+    ) -> Result<ApiResponse<HashMap<String, Presence>>, AppError> {
+        let mut conn = get_conn!(state);
+
+        // Users who hide presence and private accounts viewer doesn't follow are left out
+        let settings =
+            get_visible_users_settings(&session.user_id, &payload.user_ids, &mut conn).await;
+        let user_ids: Vec<String> = settings
+            .into_iter()
+            .filter(|(user_id, settings)| {
+                user_id == &session.user_id || settings.privacy.show_presence
+            })
+            .map(|(user_id, _)| user_id)
+            .collect();
+
+        let presences = get_presences(&user_ids, &state).await;
+        let result = user_ids.into_iter().zip(presences).collect();
+
+        Ok(response(result, StatusCode::OK))
+    }
+}
+
+mod follow {
+    use axum::extract::Path;
+
+    use super::*;
+    use crate::database::{
+        blocks::is_blocked_between,
+        follows::{create_follow_request, follow_user},
+        notifications::create_notification,
+        settings::get_user_settings,
+    };
+
+    #[derive(Debug, Serialize)]
+    pub struct Returns {
+        /// "following" or "requested" for private accounts
+        pub status: &'static str,
+    }
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<ApiResponse<Returns>, AppError> {
+        if user_id == session.user_id {
+            return Err(FuncError::IncorrectData.into());
+        }
+
+        let mut conn = get_conn!(state);
+        let target = get_user(&user_id, &mut conn)
+            .await
+            .ok_or(FuncError::UserNotFound)?;
+        if is_blocked_between(&session.user_id, &user_id, &mut conn).await {
+            return Err(FuncError::Forbidden.into());
+        }
+
+        if is_following(&session.user_id, &user_id, &mut conn).await {
+            return Ok(response(Returns { status: "following" }, StatusCode::OK));
+        }
+        let notifications = get_user_settings(&user_id, &mut conn, &state)
+            .await
+            .notifications;
+
+        let mut tx = create_tx!(conn);
+        // Private accounts have to approve follow first
+        let (status, r#type, created) = if target.is_private == Some(true) {
+            let created = create_follow_request(&session.user_id, &user_id, &mut tx).await;
+            ("requested", "follow_request", created)
+        } else {
+            let created = follow_user(&session.user_id, &user_id, &mut tx).await;
+            ("following", "follow", created)
+        };
+        if created && notifications.allows(r#type) {
+            create_notification(&user_id, &session.user_id, r#type, None, &mut tx).await;
+        }
+        tx.commit().await.unwrap();
+
+        if created && status == "following" {
+            invalidate_timeline(&session.user_id, &state).await;
+        }
+        Ok(response(Returns { status }, StatusCode::OK))
+    }
+}
+
+mod unfollow {
+    use axum::extract::Path;
+
+    use super::*;
+    use crate::database::follows::{remove_follow_request, unfollow_user};
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let mut tx = create_tx!(conn);
+
+        // Also cancels pending follow request
+        let unfollowed = unfollow_user(&session.user_id, &user_id, &mut tx).await;
+        let dirty = remove_follow_request(&session.user_id, &user_id, &mut tx).await || unfollowed;
+
+        if dirty {
+            tx.commit().await.unwrap();
+        }
+        if unfollowed {
+            invalidate_timeline(&session.user_id, &state).await;
+        }
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+mod follow_requests {
+    use super::*;
+    use crate::database::follows::{FollowRequest, get_follow_requests};
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+    ) -> Result<ApiResponse<Vec<FollowRequest>>, AppError> {
+        let mut conn = get_conn!(state);
+        let requests = get_follow_requests(&session.user_id, &mut conn).await;
+
+        Ok(response(requests, StatusCode::OK))
+    }
+}
+
+mod approve_follow_request {
+    use axum::extract::Path;
+
+    use super::*;
+    use crate::database::{
+        follows::{follow_user, remove_follow_request},
+        notifications::create_notification,
+        settings::get_user_settings,
+    };
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let notifications = get_user_settings(&user_id, &mut conn, &state)
+            .await
+            .notifications;
+        let mut tx = create_tx!(conn);
+
+        if !remove_follow_request(&user_id, &session.user_id, &mut tx).await {
+            return Err(FuncError::FollowRequestNotFound.into());
+        }
+        follow_user(&user_id, &session.user_id, &mut tx).await;
+        if notifications.allows("follow_accepted") {
+            create_notification(&user_id, &session.user_id, "follow_accepted", None, &mut tx)
+                .await;
+        }
+        tx.commit().await.unwrap();
+        invalidate_timeline(&user_id, &state).await;
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+mod reject_follow_request {
+    use axum::extract::Path;
+
+    use super::*;
+    use crate::database::follows::remove_follow_request;
+
+    pub async fn handler(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+        let mut tx = create_tx!(conn);
+
+        if !remove_follow_request(&user_id, &session.user_id, &mut tx).await {
+            return Err(FuncError::FollowRequestNotFound.into());
+        }
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Block or unblock user, blocking removes follows in both directions
+mod block {
+    use axum::extract::Path;
+
+    use super::*;
+    use crate::database::blocks::{block_user, unblock_user};
+
+    pub async fn block(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        if user_id == session.user_id {
+            return Err(FuncError::IncorrectData.into());
+        }
+        let mut conn = get_conn!(state);
+        if get_min_user(&user_id, &mut conn).await.is_none() {
+            return Err(FuncError::UserNotFound.into());
+        }
+
+        let mut tx = create_tx!(conn);
+        let created = block_user(&session.user_id, &user_id, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        if created {
+            invalidate_timeline(&session.user_id, &state).await;
+            invalidate_timeline(&user_id, &state).await;
+        }
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn unblock(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        let mut tx = create_tx!(conn);
+        unblock_user(&session.user_id, &user_id, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
+/// Mute or unmute user, muted users are hidden from feeds
+mod mute {
+    use axum::extract::Path;
+
+    use super::*;
+    use crate::database::blocks::{mute_user, unmute_user};
+
+    pub async fn mute(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        if user_id == session.user_id {
+            return Err(FuncError::IncorrectData.into());
+        }
+        let mut conn = get_conn!(state);
+        if get_min_user(&user_id, &mut conn).await.is_none() {
+            return Err(FuncError::UserNotFound.into());
+        }
+
+        let mut tx = create_tx!(conn);
+        mute_user(&session.user_id, &user_id, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+
+    pub async fn unmute(
+        session: AuthSession,
+        State(state): State<ArcAppState>,
+        Path(user_id): Path<String>,
+    ) -> Result<StatusCode, AppError> {
+        let mut conn = get_conn!(state);
+
+        let mut tx = create_tx!(conn);
+        unmute_user(&session.user_id, &user_id, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        Ok(StatusCode::NO_CONTENT)
+    }
+}
+
 pub fn router() -> Router<ArcAppState> {
     Router::new()
         .route("/me", get(me::handler).patch(patch_me::handler))
+        .route("/me/username", put(change_username::handler))
+        .route(
+            "/me/settings",
+            get(settings::handler).patch(patch_settings::handler),
+        )
+        .route("/me/follow-requests", get(follow_requests::handler))
+        .route(
+            "/me/follow-requests/{user_id}",
+            post(approve_follow_request::handler).delete(reject_follow_request::handler),
+        )
+        .route("/me/favorites", get(favorites::handler))
+        .route("/me/presence", post(heartbeat::handler))
+        .route("/presence", post(bulk_presence::handler))
+        .route("/username/{username}", get(get_by_username::handler))
         .route("/{user_id}", get(get_user::handler))
+        .route("/{user_id}/posts", get(user_posts::handler))
+        .route(
+            "/{user_id}/follow",
+            post(follow::handler).delete(unfollow::handler),
+        )
+        .route(
+            "/{user_id}/block",
+            post(block::block).delete(block::unblock),
+        )
+        .route("/{user_id}/mute", post(mute::mute).delete(mute::unmute))
 }
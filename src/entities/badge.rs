@@ -0,0 +1,12 @@
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+
+#[skip_serializing_none]
+#[derive(Serialize, Debug)]
+pub struct Badge {
+    pub badge_id: i16,
+    pub name: String,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+    pub created_at: i64,
+}
@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+/// Types of file contexts, stored as 'type' in files table
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileType {
+    Avatar,
+    Banner,
+    PostImage,
+    PostVideo,
+    BadgeIcon,
+}
+
+impl FileType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileType::Avatar => "avatar",
+            FileType::Banner => "banner",
+            FileType::PostImage => "post_image",
+            FileType::PostVideo => "post_video",
+            FileType::BadgeIcon => "badge_icon",
+        }
+    }
+
+    /// Max size of a single object in bytes
+    pub fn max_size(&self) -> u64 {
+        match self {
+            FileType::Avatar => 5 * 1024 * 1024,
+            FileType::Banner => 10 * 1024 * 1024,
+            FileType::PostImage => 10 * 1024 * 1024,
+            FileType::PostVideo => 200 * 1024 * 1024,
+            FileType::BadgeIcon => 1024 * 1024,
+        }
+    }
+
+    /// Max amount of objects in one context
+    pub fn max_count(&self) -> usize {
+        match self {
+            FileType::PostImage => 10,
+            _ => 1,
+        }
+    }
+
+    /// Content type that CDN will accept for objects
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            FileType::PostVideo => "video",
+            _ => "image",
+        }
+    }
+
+    /// Directory for objects, avatars and banners are public so they don't need signed links
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            FileType::Avatar => "public/avatars",
+            FileType::Banner => "public/banners",
+            FileType::BadgeIcon => "public/badges",
+            FileType::PostImage | FileType::PostVideo => "posts",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileContext {
+    pub context_id: String,
+    pub user_id: String,
+    pub objects: Vec<String>,
+    pub r#type: String,
+    pub reference_count: i32,
+    pub allowed_count: i32,
+}
+
+impl FileContext {
+    /// Check that context belongs to user_id and has one of the types
+    pub fn is_usable_by(&self, user_id: &str, types: &[FileType]) -> bool {
+        self.user_id == user_id && types.iter().any(|t| t.as_str() == self.r#type)
+    }
+}
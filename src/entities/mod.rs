@@ -1,2 +1,9 @@
+pub mod badge;
+pub mod file;
+pub mod poll;
 pub mod post;
+pub mod reaction;
+pub mod role;
+pub mod settings;
+pub mod tag;
 pub mod user;
@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Poll options are limited to this range
+pub const POLL_MIN_OPTIONS: u64 = 2;
+pub const POLL_MAX_OPTIONS: u64 = 10;
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PollOption {
+    pub text: String,
+    pub votes_count: Option<i64>,
+}
+
+/// Poll attached to post, counts are missing while results are hidden from viewer
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Poll {
+    pub options: Vec<PollOption>,
+    pub multiple_choice: bool,
+    pub hide_results: bool,
+    pub expires_at: i64,
+    /// Poll expired and takes no more votes
+    pub closed: bool,
+    /// Viewer can see counts: results aren't hidden, viewer voted or is author, or poll is closed
+    pub results_visible: bool,
+    pub voters_count: Option<i64>,
+    #[serde(default)]
+    pub votes_count: Option<i64>,
+    /// Positions of options viewer voted for
+    pub own_vote: Option<Vec<i16>>,
+}
+
+impl Poll {
+    /// Fills total of votes, or clears all counts if results are hidden from viewer
+    pub fn with_totals(mut self) -> Self {
+        if self.results_visible {
+            self.votes_count = Some(self.options.iter().filter_map(|o| o.votes_count).sum());
+        } else {
+            self.voters_count = None;
+            self.votes_count = None;
+            self.options.iter_mut().for_each(|o| o.votes_count = None);
+        }
+        self
+    }
+}
@@ -1,8 +1,22 @@
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
 
+use crate::entities::{poll::Poll, reaction::Reaction};
+
+/// Flags that author can set on post
+pub const POST_FLAGS: &[&str] = &["sensitive", "spoiler"];
+
+/// Who can see post, the author always can
+/// Unlisted posts are visible like public ones, but left out of search, tags and recommendations
+pub const POST_VISIBILITIES: &[&str] = &["public", "followers", "friends", "unlisted", "private"];
+
+/// Post types, reposts have no content and quotes have own content, both reference original
+pub const POST_TYPE_POST: &str = "post";
+pub const POST_TYPE_REPOST: &str = "repost";
+pub const POST_TYPE_QUOTE: &str = "quote";
+
 #[skip_serializing_none]
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Post {
     pub post_id: String,
     pub user_id: String,
@@ -15,7 +29,55 @@ pub struct Post {
     pub flags: Vec<String>,
     pub media: Vec<String>,
     pub media_type: Option<String>,
+    /// "active", or "draft" and "scheduled" for posts that aren't published yet
     pub status: Option<String>,
+    /// When scheduled post goes live
+    pub publish_at: Option<i64>,
+    pub visibility: String,
     pub is_deleted: Option<bool>,
     pub tags: Option<Vec<String>>,
+    /// Reaction of the viewer
+    pub reaction: Option<Reaction>,
+    pub is_favorited: bool,
+    pub revisions_count: i32,
+    /// Content or tags were changed after publishing
+    pub edited: bool,
+    pub post_type: String,
+    pub reference_post_id: Option<String>,
+    /// Original of repost or quote, missing if it was deleted or is hidden from viewer
+    pub reference: Option<Box<Post>>,
+    pub reposts_count: i64,
+    pub quotes_count: i64,
+    /// Viewer reposted this post
+    pub is_reposted: bool,
+    pub poll: Option<Poll>,
+    /// Mentions, hashtags and urls in content, sorted by position
+    pub entities: Vec<ContentEntity>,
+}
+
+/// Entity in post content, 'start' and 'end' are byte offsets
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ContentEntity {
+    /// "mention", "hashtag" or "url"
+    pub r#type: String,
+    pub start: usize,
+    pub end: usize,
+    /// Username, normalized tag or link
+    pub value: String,
+    /// Mentioned user
+    pub user_id: Option<String>,
+}
+
+/// Previous version of edited post
+#[derive(Serialize, Debug)]
+pub struct PostRevision {
+    pub revision_id: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    pub visibility: String,
+    pub media: Vec<String>,
+    pub media_type: Option<String>,
+    /// When this version was published
+    pub created_at: i64,
 }
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+/// Reaction on post or comment, stored as 'is_like' in reactions table
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Reaction {
+    Like,
+    Dislike,
+}
+
+impl Reaction {
+    pub fn is_like(&self) -> bool {
+        *self == Reaction::Like
+    }
+
+    pub fn from_is_like(is_like: bool) -> Self {
+        if is_like {
+            Reaction::Like
+        } else {
+            Reaction::Dislike
+        }
+    }
+}
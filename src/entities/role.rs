@@ -0,0 +1,10 @@
+use serde::Serialize;
+
+#[derive(Serialize, Debug)]
+pub struct Role {
+    pub role_id: i32,
+    pub name: String,
+    /// Roles are managed only by users of higher rank
+    pub rank: i32,
+    pub permissions: Vec<&'static str>,
+}
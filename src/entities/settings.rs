@@ -0,0 +1,123 @@
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+/// Who can start direct messages with user
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DmPrivacy {
+    #[default]
+    Everyone,
+    Followers,
+    Friends,
+    Nobody,
+}
+
+fn validate_muted_words(words: &Vec<String>) -> Result<(), ValidationError> {
+    if words.len() > 64 {
+        return Err(ValidationError::new("too_many_muted_words"));
+    }
+    for word in words {
+        if word.is_empty() || word.len() > 64 {
+            return Err(ValidationError::new("invalid_muted_word"));
+        }
+    }
+    Ok(())
+}
+
+/// Filters of posts in feeds, applied in SQL by POST_NOT_FILTERED
+#[derive(Serialize, Deserialize, Debug, Clone, Validate)]
+#[serde(default)]
+pub struct ContentFilters {
+    /// Hide posts flagged as sensitive
+    pub hide_sensitive: bool,
+    /// Hide posts containing any of these words, case insensitive
+    #[validate(custom(function = "validate_muted_words"))]
+    pub muted_words: Vec<String>,
+}
+
+impl Default for ContentFilters {
+    fn default() -> Self {
+        Self {
+            hide_sensitive: true,
+            muted_words: Vec::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct NotificationSettings {
+    pub follows: bool,
+    pub mentions: bool,
+    pub reactions: bool,
+    pub comments: bool,
+    pub messages: bool,
+    pub polls: bool,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            follows: true,
+            mentions: true,
+            reactions: true,
+            comments: true,
+            messages: true,
+            polls: true,
+        }
+    }
+}
+
+impl NotificationSettings {
+    /// Check if user wants to receive notifications of this type
+    pub fn allows(&self, r#type: &str) -> bool {
+        match r#type {
+            "follow" | "follow_request" | "follow_accepted" => self.follows,
+            "mention" => self.mentions,
+            "reaction" => self.reactions,
+            "comment" | "reply" => self.comments,
+            "message" => self.messages,
+            "poll_closed" => self.polls,
+            _ => true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct PrivacySettings {
+    /// Show online status and last seen time to others
+    pub show_presence: bool,
+}
+
+impl Default for PrivacySettings {
+    fn default() -> Self {
+        Self {
+            show_presence: true,
+        }
+    }
+}
+
+/// Per-user client preferences, stored as JSONB in user_settings
+/// Missing fields are filled with defaults, so old rows stay valid when fields are added
+#[derive(Serialize, Deserialize, Debug, Clone, Default, Validate)]
+#[serde(default)]
+pub struct UserSettings {
+    pub theme: Theme,
+    #[validate(length(min = 2, max = 16))]
+    pub language: Option<String>,
+    #[validate(nested)]
+    pub content_filters: ContentFilters,
+    pub notifications: NotificationSettings,
+    pub dm_privacy: DmPrivacy,
+    pub privacy: PrivacySettings,
+}
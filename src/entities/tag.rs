@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Debug)]
+pub struct Tag {
+    pub tag_id: String,
+    pub name: String,
+    pub posts_count: i64,
+    pub created_at: i64,
+}
+
+/// Tag usage in current window compared to the previous one of the same length
+#[derive(Serialize, Deserialize, Debug)]
+pub struct TrendingTag {
+    pub name: String,
+    pub recent_count: i64,
+    pub previous_count: i64,
+    pub score: f64,
+}
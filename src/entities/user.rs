@@ -37,6 +37,7 @@ pub struct User {
     pub bio: Option<String>,
     pub badges: Option<Vec<i16>>,
     pub languages: Option<Vec<String>>,
+    pub is_private: Option<bool>,
 }
 
 impl User {
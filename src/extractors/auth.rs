@@ -4,7 +4,7 @@ use axum::{
 };
 
 use crate::{
-    database::{auth::check_session_secret, conn::LazyConn},
+    database::{auth::check_session_secret, conn::LazyConn, presence::touch_presence},
     get_conn,
     utils::{
         response::{AppError, FuncError},
@@ -52,6 +52,10 @@ impl FromRequestParts<ArcAppState> for AuthSession {
             return Err(FuncError::InvalidToken.into());
         }
 
+        // Every authenticated request counts as presence heartbeat
+        let user_id = decoded.user_id.clone();
+        tokio::spawn(async move { touch_presence(&user_id, &app).await });
+
         Ok(AuthSession {
             user_id: decoded.user_id,
             session_id: decoded.session_id,
@@ -0,0 +1,48 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::{
+    database::{conn::LazyConn, roles::get_user_permissions},
+    extractors::auth::AuthSession,
+    get_conn,
+    utils::{
+        perms::Permission,
+        response::{AppError, FuncError},
+        state::ArcAppState,
+    },
+};
+
+/// Authenticated session of user that has all permissions in P
+/// Usage: RequirePerm<{ Permission::ADMIN_PANEL.bits() }>
+/// RequirePerm<0> only loads permissions without requiring any
+/// Resolved permissions are also put into request extensions
+#[derive(Debug)]
+pub struct RequirePerm<const P: u32> {
+    pub session: AuthSession,
+    pub permissions: Permission,
+}
+
+impl<const P: u32> FromRequestParts<ArcAppState> for RequirePerm<P> {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &ArcAppState,
+    ) -> Result<Self, Self::Rejection> {
+        let session = AuthSession::from_request_parts(parts, state).await?;
+
+        let mut conn = get_conn!(state);
+        let permissions = get_user_permissions(&session.user_id, &mut conn, state)
+            .await
+            .ok_or(FuncError::UserNotFound)?;
+
+        if !permissions.contains(Permission::from_bits_retain(P)) {
+            return Err(FuncError::Forbidden.into());
+        }
+        parts.extensions.insert(permissions);
+
+        Ok(RequirePerm {
+            session,
+            permissions,
+        })
+    }
+}
@@ -55,6 +55,7 @@ async fn main() {
         }
     };
     let shared_state = Arc::new(state);
+    services::start(shared_state.clone());
 
     let v1_router: Router<()> = endpoints::create_router()
         .route("/ping", get(ping).post(ping))
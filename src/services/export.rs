@@ -0,0 +1,132 @@
+use std::{io::Write, time::Duration};
+
+use chrono::Utc;
+use serde_json::{Value, json};
+use tracing::{error, info};
+use zip::{ZipWriter, write::SimpleFileOptions};
+
+use crate::{
+    create_tx,
+    database::{
+        conn::LazyConn,
+        exports::{
+            complete_export, expire_exports, fail_export, fail_stale_exports, get_user_data,
+        },
+        notifications::create_notification,
+    },
+    get_conn,
+    utils::{state::ArcAppState, storage::build_get_link},
+};
+
+/// Version of archive layout, bump when sections change incompatibly
+const EXPORT_VERSION: u32 = 1;
+
+/// Fields with CDN objects that are replaced by signed links
+const MEDIA_FIELDS: &[&str] = &["media", "avatar", "banner", "objects"];
+
+pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Exports pending for longer than this are considered lost
+const EXPORT_TIMEOUT_MINUTES: i64 = 60;
+
+/// Private function that replaces CDN objects in export rows with links
+fn build_media_links(value: &mut Value, state: &ArcAppState) {
+    match value {
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| build_media_links(item, state)),
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                if let (true, Value::Array(objects)) =
+                    (MEDIA_FIELDS.contains(&key.as_str()), &field)
+                {
+                    let links: Vec<Value> = objects
+                        .iter()
+                        .filter_map(|o| o.as_str())
+                        .map(|o| {
+                            Value::String(build_get_link(
+                                o,
+                                state.config.export_expires_days,
+                                state,
+                            ))
+                        })
+                        .collect();
+                    *field = Value::Array(links);
+                } else {
+                    build_media_links(field, state);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Private function for packing archive into zip
+fn zip_archive(json: &[u8]) -> zip::result::ZipResult<Vec<u8>> {
+    let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    writer.start_file("linkverse-export.json", SimpleFileOptions::default())?;
+    writer.write_all(json)?;
+    Ok(writer.finish()?.into_inner())
+}
+
+/// Private function that builds archive and stores it
+async fn build_export(state: ArcAppState, export_id: String, user_id: String, zip: bool) {
+    let mut conn = get_conn!(state);
+
+    let mut data = Value::Object(get_user_data(&user_id, &mut conn).await);
+    build_media_links(&mut data, &state);
+
+    let archive = json!({
+        "version": EXPORT_VERSION,
+        "generated_at": Utc::now().timestamp(),
+        "user_id": user_id,
+        "data": data,
+    });
+    let mut archive = serde_json::to_vec_pretty(&archive).unwrap();
+    if zip {
+        archive = zip_archive(&archive).unwrap();
+    }
+
+    let mut tx = create_tx!(conn);
+    complete_export(
+        &export_id,
+        &archive,
+        state.config.export_expires_days,
+        &mut tx,
+    )
+    .await;
+    create_notification(
+        &user_id,
+        &user_id,
+        "data_export",
+        Some(("data_export", &export_id)),
+        &mut tx,
+    )
+    .await;
+    tx.commit().await.unwrap();
+}
+
+/// Runs export job in background, export is marked as failed if job panics
+pub fn spawn_export(state: ArcAppState, export_id: String, user_id: String, zip: bool) {
+    tokio::spawn(async move {
+        let job = tokio::spawn(build_export(state.clone(), export_id.clone(), user_id, zip));
+        if let Err(err) = job.await {
+            error!("Export {} failed: {:?}", export_id, err);
+            let mut conn = get_conn!(state);
+            fail_export(&export_id, &mut conn).await;
+        }
+    });
+}
+
+/// Drops expired export archives and fails exports whose jobs were lost
+pub async fn cleanup(state: ArcAppState) {
+    let mut conn = get_conn!(state);
+    let expired = expire_exports(&mut conn).await;
+    if expired > 0 {
+        info!("Expired {} data exports", expired);
+    }
+    let stale = fail_stale_exports(EXPORT_TIMEOUT_MINUTES, &mut conn).await;
+    if stale > 0 {
+        info!("Failed {} stale data exports", stale);
+    }
+}
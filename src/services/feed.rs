@@ -0,0 +1,37 @@
+use crate::{
+    database::{
+        conn::LazyConn,
+        follows::{get_follower_ids, get_followers_count},
+        posts::{get_feed_id, get_timeline_seed},
+        timelines::{TIMELINE_SIZE, push_to_timelines},
+    },
+    get_conn,
+    utils::state::ArcAppState,
+};
+
+/// Private function that pushes new post to timelines of author and followers
+/// Followers of large accounts are skipped, their feeds merge those posts at read time
+async fn fanout(state: ArcAppState, post_id: String, author_id: String) {
+    let mut conn = get_conn!(state);
+    let Some(feed_id) = get_feed_id(&post_id, &mut conn).await else {
+        return;
+    };
+
+    let mut user_ids = vec![author_id.clone()];
+    if get_followers_count(&author_id, &mut conn).await < state.config.feed_fanout_limit {
+        user_ids.extend(get_follower_ids(&author_id, &mut conn).await);
+    }
+    push_to_timelines(&[(post_id, feed_id)], &user_ids, &state).await;
+}
+
+/// Runs fan-out of new post in background
+pub fn spawn_fanout(state: ArcAppState, post_id: String, author_id: String) {
+    tokio::spawn(fanout(state, post_id, author_id));
+}
+
+/// Builds user's timeline from database, used when it isn't materialised
+pub async fn rebuild_timeline(user_id: &String, conn: &mut LazyConn, state: &ArcAppState) {
+    let posts =
+        get_timeline_seed(user_id, state.config.feed_fanout_limit, TIMELINE_SIZE, conn).await;
+    push_to_timelines(&posts, std::slice::from_ref(user_id), state).await;
+}
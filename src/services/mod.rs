@@ -0,0 +1,48 @@
+use std::{future::Future, time::Duration};
+
+use tracing::error;
+
+use crate::utils::state::ArcAppState;
+
+pub mod export;
+pub mod feed;
+pub mod polls;
+pub mod popular;
+pub mod publisher;
+pub mod purge;
+pub mod views;
+
+/// Runs job every 'period', every run is a separate task so panic doesn't stop the loop
+fn spawn_periodic<F, Fut>(state: ArcAppState, period: Duration, job: F)
+where
+    F: Fn(ArcAppState) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if let Err(err) = tokio::spawn(job(state.clone())).await {
+                error!("Periodic job failed: {:?}", err);
+            }
+        }
+    });
+}
+
+/// Starts background services
+pub fn start(state: ArcAppState) {
+    spawn_periodic(state.clone(), export::CLEANUP_INTERVAL, export::cleanup);
+    spawn_periodic(
+        state.clone(),
+        popular::refresh_interval(&state),
+        popular::refresh,
+    );
+    spawn_periodic(state.clone(), views::flush_interval(&state), views::flush);
+    spawn_periodic(state.clone(), polls::CLOSE_INTERVAL, polls::close);
+    spawn_periodic(
+        state.clone(),
+        publisher::PUBLISH_INTERVAL,
+        publisher::publish_due,
+    );
+    spawn_periodic(state.clone(), purge::PURGE_INTERVAL, purge::purge);
+}
@@ -0,0 +1,61 @@
+use std::{collections::HashSet, time::Duration};
+
+use tracing::info;
+
+use crate::{
+    create_tx,
+    database::{
+        conn::LazyConn, notifications::create_notifications, polls::close_expired_polls,
+        settings::get_users_settings,
+    },
+    get_conn,
+    utils::state::ArcAppState,
+};
+
+pub const CLOSE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Polls closed per transaction
+const CLOSE_BATCH: i64 = 100;
+
+/// Closes expired polls and notifies their authors and voters,
+/// unless they turned poll notifications off
+pub async fn close(state: ArcAppState) {
+    let mut conn = get_conn!(state);
+    loop {
+        let mut tx = create_tx!(conn);
+        let closed = close_expired_polls(CLOSE_BATCH, &mut tx).await;
+
+        let recipients: Vec<String> = closed
+            .iter()
+            .flat_map(|(_, author_id, voters)| voters.iter().chain([author_id]))
+            .cloned()
+            .collect();
+        let muted: HashSet<String> = get_users_settings(&recipients, &mut tx)
+            .await
+            .into_iter()
+            .filter(|(_, settings)| !settings.notifications.allows("poll_closed"))
+            .map(|(user_id, _)| user_id)
+            .collect();
+
+        for (post_id, author_id, mut voters) in closed.iter().cloned() {
+            voters.push(author_id.clone());
+            voters.retain(|user_id| !muted.contains(user_id));
+            create_notifications(
+                &voters,
+                &author_id,
+                "poll_closed",
+                Some(("post", &post_id)),
+                &mut tx,
+            )
+            .await;
+        }
+        tx.commit().await.unwrap();
+
+        if !closed.is_empty() {
+            info!("Closed {} polls", closed.len());
+        }
+        if (closed.len() as i64) < CLOSE_BATCH {
+            break;
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use std::time::Duration;
+
+use chrono::Utc;
+use tracing::info;
+
+use crate::{
+    database::{
+        conn::LazyConn,
+        popular::{POPULAR_SIZE, acquire_refresh_lock, store_snapshot},
+        posts::get_popular_ranking,
+    },
+    get_conn,
+    utils::state::ArcAppState,
+};
+
+pub fn refresh_interval(state: &ArcAppState) -> Duration {
+    Duration::from_secs(state.config.popular_refresh_secs)
+}
+
+/// Recomputes ranked snapshot of popular posts
+/// Lock lives a bit less than the interval, so only one server refreshes per period
+pub async fn refresh(state: ArcAppState) {
+    let lock_ttl = (state.config.popular_refresh_secs as i64 - 1).max(1);
+    if !acquire_refresh_lock(lock_ttl, &state).await {
+        return;
+    }
+
+    let mut conn = get_conn!(state);
+    let ranking = get_popular_ranking(
+        state.config.popular_gravity,
+        state.config.popular_window_hours,
+        POPULAR_SIZE,
+        &mut conn,
+    )
+    .await;
+
+    let snapshot = Utc::now().timestamp_millis().to_string();
+    store_snapshot(&snapshot, &ranking, &state).await;
+    info!(
+        "Stored popular snapshot {} with {} posts",
+        snapshot,
+        ranking.len()
+    );
+}
@@ -0,0 +1,169 @@
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::{
+    create_tx,
+    database::{
+        conn::LazyConn,
+        mentions::set_mentions,
+        notifications::create_notifications,
+        posts::{filter_post_viewers, lock_due_posts, publish_post},
+        settings::get_user_settings,
+    },
+    get_conn,
+    services::feed::spawn_fanout,
+    utils::{state::ArcAppState, text::extract_mentions},
+};
+
+pub const PUBLISH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Scheduled posts published per transaction
+const PUBLISH_BATCH: i64 = 100;
+
+/// Notifies mentioned users, unless they turned mention notifications off
+/// Users who can't see the post, e.g. non-followers for followers-only post, are skipped
+pub async fn notify_mentions(
+    user_ids: &[String],
+    author_id: &str,
+    post_id: &str,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) {
+    if user_ids.is_empty() {
+        return;
+    }
+    let mut recipients: Vec<String> = Vec::new();
+    for user_id in filter_post_viewers(post_id, user_ids, conn).await {
+        let settings = get_user_settings(&user_id, conn, state).await;
+        if settings.notifications.allows("mention") {
+            recipients.push(user_id);
+        }
+    }
+
+    let mut tx = create_tx!(conn);
+    create_notifications(
+        &recipients,
+        author_id,
+        "mention",
+        Some(("post", post_id)),
+        &mut tx,
+    )
+    .await;
+    tx.commit().await.unwrap();
+}
+
+/// Publishes draft or scheduled post, then notifies mentioned users and fans it out
+/// Returns false if it was already published
+pub async fn publish(
+    post_id: &String,
+    author_id: &String,
+    content: &str,
+    conn: &mut LazyConn,
+    state: &ArcAppState,
+) -> bool {
+    let mut tx = create_tx!(conn);
+    if !publish_post(post_id, &mut tx).await {
+        return false;
+    }
+    let mentioned = set_mentions(
+        author_id,
+        post_id,
+        None,
+        &extract_mentions(content),
+        &mut tx,
+    )
+    .await;
+    tx.commit().await.unwrap();
+
+    notify_mentions(&mentioned, author_id, post_id, conn, state).await;
+    spawn_fanout(state.clone(), post_id.clone(), author_id.clone());
+    true
+}
+
+/// Publishes scheduled posts that are due
+/// Due posts stay locked while they are published, so several servers never publish one twice
+pub async fn publish_due(state: ArcAppState) {
+    let mut conn = get_conn!(state);
+    loop {
+        let mut tx = create_tx!(conn);
+        let due = lock_due_posts(PUBLISH_BATCH, &mut tx).await;
+        let mut published = Vec::with_capacity(due.len());
+        for (post_id, author_id, content) in &due {
+            if publish_post(post_id, &mut tx).await {
+                let mentions = extract_mentions(content);
+                let mentioned = set_mentions(author_id, post_id, None, &mentions, &mut tx).await;
+                published.push((post_id, author_id, mentioned));
+            }
+        }
+        tx.commit().await.unwrap();
+
+        for (post_id, author_id, mentioned) in &published {
+            notify_mentions(mentioned, author_id, post_id, &mut conn, &state).await;
+            spawn_fanout(state.clone(), post_id.to_string(), author_id.to_string());
+        }
+        if !published.is_empty() {
+            info!("Published {} scheduled posts", published.len());
+        }
+        if (due.len() as i64) < PUBLISH_BATCH {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        database::{
+            follows::follow_user,
+            posts::create_post,
+            test_support::{cleanup, new_user},
+        },
+        utils::state::AppState,
+    };
+
+    #[tokio::test]
+    #[ignore = "requires local Redis, see AppState::for_redis_tests"]
+    async fn mentions_respect_post_visibility() {
+        let state = Arc::new(AppState::for_redis_tests().await);
+        let mut conn = get_conn!(state);
+        let author = new_user(&mut conn).await;
+        let follower = new_user(&mut conn).await;
+        let stranger = new_user(&mut conn).await;
+
+        let mut tx = create_tx!(conn);
+        follow_user(&follower, &author, &mut tx).await;
+        let post_id = create_post(
+            &author,
+            &"content".to_string(),
+            &None,
+            &vec![],
+            &None,
+            "followers",
+            &mut tx,
+        )
+        .await;
+        tx.commit().await.unwrap();
+
+        let mentioned = vec![follower.clone(), stranger.clone()];
+        notify_mentions(&mentioned, &author, &post_id, &mut conn, &state).await;
+
+        let db = conn.get_client().await.unwrap();
+        let notified: Vec<String> = db
+            .query(
+                "SELECT user_id FROM user_notifications WHERE type = 'mention' AND linked_id = $1",
+                &[&post_id],
+            )
+            .await
+            .unwrap()
+            .iter()
+            .map(|r| r.get(0))
+            .collect();
+        assert_eq!(notified, vec![follower.clone()]);
+
+        cleanup(&[&author, &follower, &stranger], &mut conn).await;
+    }
+}
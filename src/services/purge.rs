@@ -0,0 +1,39 @@
+use std::time::Duration;
+
+use tracing::info;
+
+use crate::{
+    create_tx,
+    database::{conn::LazyConn, posts::purge_deleted_posts},
+    get_conn,
+    utils::state::ArcAppState,
+};
+
+pub const PURGE_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Posts purged per transaction, keeps cascades of a single batch short
+const PURGE_BATCH: i64 = 100;
+
+/// Hard deletes posts that stayed soft deleted past retention
+/// Retention never ends before restore window, so restorable posts are kept
+pub async fn purge(state: ArcAppState) {
+    let retention_days = state
+        .config
+        .post_purge_days
+        .max(state.config.post_restore_days);
+    let mut conn = get_conn!(state);
+    let mut total = 0;
+    loop {
+        let mut tx = create_tx!(conn);
+        let purged = purge_deleted_posts(retention_days, PURGE_BATCH, &mut tx).await;
+        tx.commit().await.unwrap();
+
+        total += purged;
+        if (purged as i64) < PURGE_BATCH {
+            break;
+        }
+    }
+    if total > 0 {
+        info!("Purged {} deleted posts", total);
+    }
+}
@@ -0,0 +1,24 @@
+use std::time::Duration;
+
+use crate::{
+    database::{
+        conn::LazyConn,
+        views::{insert_views, take_buffered_views},
+    },
+    get_conn,
+    utils::state::ArcAppState,
+};
+
+pub fn flush_interval(state: &ArcAppState) -> Duration {
+    Duration::from_secs(state.config.views_flush_secs)
+}
+
+/// Moves buffered post views from Redis to database
+pub async fn flush(state: ArcAppState) {
+    let views = take_buffered_views(&state).await;
+    if views.is_empty() {
+        return;
+    }
+    let mut conn = get_conn!(state);
+    insert_views(&views, &mut conn).await;
+}
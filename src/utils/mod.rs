@@ -1,9 +1,12 @@
+pub mod json;
 pub mod macros;
+pub mod pagination;
 pub mod perms;
 pub mod response;
 pub mod security;
 pub mod snowflake;
 pub mod state;
 pub mod storage;
+pub mod text;
 pub mod thread_state;
 pub mod validate;
@@ -0,0 +1,164 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, serde_as};
+
+const DEFAULT_LIMIT: i64 = 20;
+const MAX_LIMIT: i64 = 100;
+
+fn clamp_limit(limit: Option<i64>) -> i64 {
+    limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+}
+
+/// Query for paginating by snowflake ids, e.g. ?before=123&limit=20
+/// 'before' gives older items, 'after' gives newer ones, result is always newest first
+#[derive(Debug, Deserialize, Default)]
+pub struct CursorParams {
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    pub limit: Option<i64>,
+}
+
+impl CursorParams {
+    pub fn limit(&self) -> i64 {
+        clamp_limit(self.limit)
+    }
+
+    /// Paging towards newer items is done in ascending order and reversed afterwards
+    fn is_ascending(&self) -> bool {
+        self.after.is_some() && self.before.is_none()
+    }
+
+    /// SQL condition on id column, takes params $n (before) and $n+1 (after)
+    pub fn where_clause(&self, column: &str, n: usize) -> String {
+        format!(
+            "(${0}::BIGINT IS NULL OR {2}::bigint < ${0}) AND (${1}::BIGINT IS NULL OR {2}::bigint > ${1})",
+            n,
+            n + 1,
+            column
+        )
+    }
+
+    /// SQL ordering and limit, takes param $n (limit)
+    pub fn order_clause(&self, column: &str, n: usize) -> String {
+        let order = if self.is_ascending() { "ASC" } else { "DESC" };
+        format!("ORDER BY {}::bigint {} LIMIT ${}", column, order, n)
+    }
+
+    /// Puts fetched items into newest first order
+    pub fn arrange<T>(&self, mut items: Vec<T>) -> Vec<T> {
+        if self.is_ascending() {
+            items.reverse();
+        }
+        items
+    }
+}
+
+/// Cursor for lists ordered by time (microseconds) and then by snowflake id
+/// Sent to clients as "<at>_<id>"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeCursor {
+    pub at: i64,
+    pub id: i64,
+}
+
+impl TimeCursor {
+    /// SQL expression for cursor time of timestamp column
+    pub fn sql_value(column: &str) -> String {
+        format!("(EXTRACT(EPOCH FROM {}) * 1000000)::BIGINT", column)
+    }
+
+    /// SQL condition for items older than cursor, takes params $n (at) and $n+1 (id)
+    pub fn where_clause(time_column: &str, id_column: &str, n: usize) -> String {
+        format!(
+            "(${0}::BIGINT IS NULL OR ({2}, {3}::bigint) < (${0}, ${1}::BIGINT))",
+            n,
+            n + 1,
+            Self::sql_value(time_column),
+            id_column
+        )
+    }
+}
+
+impl fmt::Display for TimeCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.at, self.id)
+    }
+}
+
+impl FromStr for TimeCursor {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (at, id) = s.split_once('_').unwrap_or((s, ""));
+        Ok(TimeCursor {
+            at: at.parse()?,
+            id: id.parse()?,
+        })
+    }
+}
+
+/// Query for paginating by TimeCursor, e.g. ?before=1700000000000000_123
+#[serde_as]
+#[derive(Debug, Deserialize, Default)]
+pub struct TimeCursorParams {
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub before: Option<TimeCursor>,
+    pub limit: Option<i64>,
+}
+
+impl TimeCursorParams {
+    pub fn limit(&self) -> i64 {
+        clamp_limit(self.limit)
+    }
+}
+
+/// Page of items with cursor for the next one, None when there are no more items
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Cursor for ranked snapshots, position in snapshot that doesn't move while it lives
+/// Sent to clients as "<snapshot>_<offset>"
+#[derive(Debug, Clone, PartialEq)]
+pub struct RankCursor {
+    pub snapshot: String,
+    pub offset: i64,
+}
+
+impl fmt::Display for RankCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.snapshot, self.offset)
+    }
+}
+
+impl FromStr for RankCursor {
+    type Err = std::num::ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (snapshot, offset) = s.split_once('_').unwrap_or((s, ""));
+        Ok(RankCursor {
+            snapshot: snapshot.parse::<i64>()?.to_string(),
+            offset: offset.parse()?,
+        })
+    }
+}
+
+/// Query for paginating ranked snapshot, e.g. ?cursor=1700000000000_20
+#[serde_as]
+#[derive(Debug, Deserialize, Default)]
+pub struct RankCursorParams {
+    #[serde_as(as = "Option<DisplayFromStr>")]
+    #[serde(default)]
+    pub cursor: Option<RankCursor>,
+    pub limit: Option<i64>,
+}
+
+impl RankCursorParams {
+    pub fn limit(&self) -> i64 {
+        clamp_limit(self.limit)
+    }
+}
@@ -1,6 +1,7 @@
 use bitflags::bitflags;
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct Permission: u32 {
         const NONE                 = 0;
         const MODERATE_POSTS       = 1 << 0;
@@ -13,66 +14,20 @@ bitflags! {
     }
 }
 
-/// User permissions by role
-/// 0 -> User
-/// 1 -> Trusted
-/// 2 -> Trusted + moderator
-/// 3 -> Moderator
-/// 4 -> Admin
-/// 999 -> Owner
-/// Trusted means someone has access to 'red button', basically to power off everything
-pub fn role_permissions(role_id: &i32) -> Permission {
-    match role_id {
-        0 => Permission::NONE,
-        1 => Permission::RED_BUTTON,
-        2 => {
-            Permission::RED_BUTTON
-                | Permission::MODERATE_POSTS
-                | Permission::MODERATE_COMMENTS
-                | Permission::MODERATE_PROFILES
-        }
-        3 => {
-            Permission::MODERATE_POSTS
-                | Permission::MODERATE_COMMENTS
-                | Permission::MODERATE_PROFILES
-        }
-        4 => {
-            Permission::RED_BUTTON
-                | Permission::MODERATE_POSTS
-                | Permission::MODERATE_COMMENTS
-                | Permission::MODERATE_PROFILES
-                | Permission::BAN_USERS
-                | Permission::ADMIN_PANEL
-        }
-        999 => Permission::all(),
-        _ => Permission::NONE,
-    }
-}
-
+/// Names of all permissions that are set, generated from flags definition
 pub fn permissions_to_list(p: Permission) -> Vec<&'static str> {
-    let mut out = Vec::new();
+    p.iter_names().map(|(name, _)| name).collect()
+}
 
-    if p.contains(Permission::MODERATE_POSTS) {
-        out.push("MODERATE_POSTS");
-    }
-    if p.contains(Permission::MODERATE_COMMENTS) {
-        out.push("MODERATE_COMMENTS");
-    }
-    if p.contains(Permission::MODERATE_PROFILES) {
-        out.push("MODERATE_PROFILES");
-    }
-    if p.contains(Permission::BAN_USERS) {
-        out.push("BAN_USERS");
-    }
-    if p.contains(Permission::RED_BUTTON) {
-        out.push("RED_BUTTON");
-    }
-    if p.contains(Permission::REVIEW_APPELLATIONS) {
-        out.push("REVIEW_APPELLATIONS");
-    }
-    if p.contains(Permission::ADMIN_PANEL) {
-        out.push("ADMIN_PANEL");
-    }
+/// Get single permission by its name, e.g. "BAN_USERS"
+pub fn permission_from_name(name: &str) -> Option<Permission> {
+    Permission::from_name(name).filter(|p| !p.is_empty())
+}
 
-    out
+/// Combine permission names stored in database into flags, unknown names are ignored
+pub fn permissions_from_names(names: &[String]) -> Permission {
+    names
+        .iter()
+        .filter_map(|name| permission_from_name(name))
+        .fold(Permission::NONE, |acc, p| acc | p)
 }
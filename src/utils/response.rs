@@ -78,6 +78,24 @@ pub enum FuncError {
     Unauthorized,
     ExpiredToken,
     InvalidToken,
+    UsernameChangeCooldown,
+    FollowRequestNotFound,
+    InvalidFileContext,
+    ExportRateLimited,
+    ExportNotFound,
+    RoleNotFound,
+    RoleExists,
+    BadgeNotFound,
+    BadgeExists,
+    PostNotFound,
+    CommentNotFound,
+    TagNotFound,
+    PollNotFound,
+    PollClosed,
+    AlreadyVoted,
+    RestoreWindowExpired,
+    CursorExpired,
+    Forbidden,
 }
 
 impl From<FuncError> for AppError {
@@ -92,6 +110,28 @@ impl From<FuncError> for AppError {
             FuncError::Unauthorized => AppError::Unauthorized("UNAUTHORIZED".into()),
             FuncError::ExpiredToken => AppError::Unauthorized("EXPIRED_TOKEN".into()),
             FuncError::InvalidToken => AppError::Unauthorized("INVALID_TOKEN".into()),
+            FuncError::UsernameChangeCooldown => {
+                AppError::Forbidden("USERNAME_CHANGE_COOLDOWN".into())
+            }
+            FuncError::FollowRequestNotFound => {
+                AppError::NotFound("FOLLOW_REQUEST_NOT_FOUND".into())
+            }
+            FuncError::InvalidFileContext => AppError::BadRequest("INVALID_FILE_CONTEXT".into()),
+            FuncError::ExportRateLimited => AppError::Forbidden("EXPORT_RATE_LIMITED".into()),
+            FuncError::ExportNotFound => AppError::NotFound("EXPORT_NOT_FOUND".into()),
+            FuncError::RoleNotFound => AppError::NotFound("ROLE_NOT_FOUND".into()),
+            FuncError::RoleExists => AppError::Conflict("ROLE_EXISTS".into()),
+            FuncError::BadgeNotFound => AppError::NotFound("BADGE_NOT_FOUND".into()),
+            FuncError::BadgeExists => AppError::Conflict("BADGE_EXISTS".into()),
+            FuncError::PostNotFound => AppError::NotFound("POST_NOT_FOUND".into()),
+            FuncError::CommentNotFound => AppError::NotFound("COMMENT_NOT_FOUND".into()),
+            FuncError::TagNotFound => AppError::NotFound("TAG_NOT_FOUND".into()),
+            FuncError::PollNotFound => AppError::NotFound("POLL_NOT_FOUND".into()),
+            FuncError::PollClosed => AppError::Forbidden("POLL_CLOSED".into()),
+            FuncError::AlreadyVoted => AppError::Conflict("ALREADY_VOTED".into()),
+            FuncError::RestoreWindowExpired => AppError::Forbidden("RESTORE_WINDOW_EXPIRED".into()),
+            FuncError::CursorExpired => AppError::BadRequest("CURSOR_EXPIRED".into()),
+            FuncError::Forbidden => AppError::Forbidden("FORBIDDEN".into()),
         }
     }
 }
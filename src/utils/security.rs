@@ -88,6 +88,23 @@ fn verify_hmac_b64(message: &str, sig_b64: &str, signature_key: &str) -> bool {
     expected.eq(sig_b64)
 }
 
+/// Signs message for using in URLs, e.g. download links that can't have headers
+pub fn sign_url_token(message: &str, signature_key: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(signature_key.as_bytes()).unwrap();
+    mac.update(message.as_bytes());
+    general_purpose::URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())
+}
+
+/// Verifies token created by 'sign_url_token'
+pub fn verify_url_token(message: &str, token: &str, signature_key: &str) -> bool {
+    let Ok(signature) = general_purpose::URL_SAFE_NO_PAD.decode(token) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(signature_key.as_bytes()).unwrap();
+    mac.update(message.as_bytes());
+    mac.verify_slice(&signature).is_ok()
+}
+
 pub async fn generate_token(
     user_id: &str,
     key_type: &str,
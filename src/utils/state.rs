@@ -35,6 +35,17 @@ pub struct Config {
     pub vapid_secret: String,
     pub vapid_pub: String,
     pub brevo_api_key: String,
+    pub username_change_cooldown_days: i64,
+    pub username_reserve_days: i64,
+    pub export_cooldown_hours: i64,
+    pub export_expires_days: i64,
+    pub post_restore_days: i64,
+    pub post_purge_days: i64,
+    pub feed_fanout_limit: i64,
+    pub popular_gravity: f64,
+    pub popular_window_hours: i64,
+    pub popular_refresh_secs: u64,
+    pub views_flush_secs: u64,
 }
 
 impl Config {
@@ -58,6 +69,50 @@ impl Config {
             vapid_secret: env::var("VAPID_SECRET").expect("VAPID_SECRET missing"),
             vapid_pub: env::var("VAPID_PUB").expect("VAPID_PUB missing"),
             brevo_api_key: env::var("BREVO_API_KEY").expect("BREVO_API_KEY missing"),
+            username_change_cooldown_days: env::var("USERNAME_CHANGE_COOLDOWN_DAYS")
+                .unwrap_or("30".to_string())
+                .parse()
+                .expect("USERNAME_CHANGE_COOLDOWN_DAYS wrong type"),
+            username_reserve_days: env::var("USERNAME_RESERVE_DAYS")
+                .unwrap_or("14".to_string())
+                .parse()
+                .expect("USERNAME_RESERVE_DAYS wrong type"),
+            export_cooldown_hours: env::var("EXPORT_COOLDOWN_HOURS")
+                .unwrap_or("24".to_string())
+                .parse()
+                .expect("EXPORT_COOLDOWN_HOURS wrong type"),
+            export_expires_days: env::var("EXPORT_EXPIRES_DAYS")
+                .unwrap_or("7".to_string())
+                .parse()
+                .expect("EXPORT_EXPIRES_DAYS wrong type"),
+            post_restore_days: env::var("POST_RESTORE_DAYS")
+                .unwrap_or("30".to_string())
+                .parse()
+                .expect("POST_RESTORE_DAYS wrong type"),
+            post_purge_days: env::var("POST_PURGE_DAYS")
+                .unwrap_or("90".to_string())
+                .parse()
+                .expect("POST_PURGE_DAYS wrong type"),
+            feed_fanout_limit: env::var("FEED_FANOUT_LIMIT")
+                .unwrap_or("10000".to_string())
+                .parse()
+                .expect("FEED_FANOUT_LIMIT wrong type"),
+            popular_gravity: env::var("POPULAR_GRAVITY")
+                .unwrap_or("1.8".to_string())
+                .parse()
+                .expect("POPULAR_GRAVITY wrong type"),
+            popular_window_hours: env::var("POPULAR_WINDOW_HOURS")
+                .unwrap_or("48".to_string())
+                .parse()
+                .expect("POPULAR_WINDOW_HOURS wrong type"),
+            popular_refresh_secs: env::var("POPULAR_REFRESH_SECS")
+                .unwrap_or("300".to_string())
+                .parse()
+                .expect("POPULAR_REFRESH_SECS wrong type"),
+            views_flush_secs: env::var("VIEWS_FLUSH_SECS")
+                .unwrap_or("10".to_string())
+                .parse()
+                .expect("VIEWS_FLUSH_SECS wrong type"),
         }
     }
 }
@@ -153,3 +208,75 @@ impl AppState {
 }
 
 pub type ArcAppState = Arc<AppState>;
+
+#[cfg(test)]
+impl AppState {
+    /// State for tests against local Postgres with sql/ schema applied
+    /// Connection is taken from $TEST_DATABASE_URL, Redis clients are never connected
+    pub fn for_tests() -> AppState {
+        let url = env::var("TEST_DATABASE_URL")
+            .unwrap_or("postgres://postgres@localhost/linkverse_test".to_string());
+        let pg_config: PgConfig = url.parse().expect("TEST_DATABASE_URL wrong format");
+        let mgr = Manager::from_config(
+            pg_config,
+            NoTls,
+            ManagerConfig {
+                recycling_method: RecyclingMethod::Fast,
+            },
+        );
+        let db_pool = Pool::builder(mgr).max_size(4).build().unwrap();
+
+        let config = Config {
+            secret_auth_key: "1:test".to_string(),
+            secret_refresh_key: "1:test".to_string(),
+            signature_key: "test".to_string(),
+            url: "localhost:8080".to_string(),
+            server_id: 0,
+            total_servers: 1,
+            cdn_secret_key: "test".to_string(),
+            cdn_secret_key_n: "1".to_string(),
+            vapid_secret: String::new(),
+            vapid_pub: String::new(),
+            brevo_api_key: String::new(),
+            username_change_cooldown_days: 30,
+            username_reserve_days: 14,
+            export_cooldown_hours: 24,
+            export_expires_days: 7,
+            post_restore_days: 30,
+            post_purge_days: 90,
+            feed_fanout_limit: 10000,
+            popular_gravity: 1.8,
+            popular_window_hours: 48,
+            popular_refresh_secs: 300,
+            views_flush_secs: 10,
+        };
+        let redis = || Arc::new(prelude::Builder::default_centralized().build().unwrap());
+
+        AppState {
+            db_pool: Arc::new(db_pool),
+            config: Arc::new(config),
+            cache_redis: redis(),
+            sessions_redis: redis(),
+            pubsub_redis: redis(),
+        }
+    }
+
+    /// State for tests that also need Redis, clients connect to $TEST_REDIS_URL
+    /// Tests only touch keys of ids they generate, so shared database is fine
+    pub async fn for_redis_tests() -> AppState {
+        let url = env::var("TEST_REDIS_URL").unwrap_or("redis://localhost:6379/15".to_string());
+        let redis = || async {
+            let config = prelude::Config::from_url(&url).expect("TEST_REDIS_URL wrong format");
+            let client = prelude::Builder::from_config(config).build().unwrap();
+            client.init().await.expect("Redis unavailable");
+            Arc::new(client)
+        };
+
+        AppState {
+            cache_redis: redis().await,
+            sessions_redis: redis().await,
+            pubsub_redis: redis().await,
+            ..Self::for_tests()
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use unicode_normalization::{UnicodeNormalization, char::is_combining_mark};
+
+/// Most tags a post can have, explicit tags go first when hashtags don't fit
+pub const MAX_TAGS: usize = 10;
+const MAX_TAG_LENGTH: usize = 64;
+
+/// Letters, digits, '_' and combining marks, so tags in scripts with vowel signs aren't cut
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || is_combining_mark(c)
+}
+
+/// Normalizes tag name, e.g. "#Rust" -> "rust", "#Ｒｕｓｔ" -> "rust"
+/// NFKC folds compatibility forms, so tags that look the same are the same tag
+/// Returns None if tag has forbidden characters or only digits
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag
+        .trim()
+        .trim_start_matches('#')
+        .nfkc()
+        .collect::<String>()
+        .to_lowercase();
+    let valid = (1..=MAX_TAG_LENGTH).contains(&tag.chars().count())
+        && tag.chars().all(is_tag_char)
+        && !tag.chars().all(|c| c.is_ascii_digit());
+    valid.then_some(tag)
+}
+
+/// Normalizes list of tags, duplicates are removed
+/// Returns None if any tag is invalid
+pub fn normalize_tags(tags: &[String]) -> Option<Vec<String>> {
+    let mut result: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize_tag(tag)?;
+        if !result.contains(&tag) {
+            result.push(tag);
+        }
+    }
+    Some(result)
+}
+
+/// Most users notified by mentions of one post
+pub const MAX_MENTIONS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EntityKind {
+    Mention,
+    Hashtag,
+    Url,
+}
+
+/// Entity found in text, 'start' and 'end' are byte offsets
+/// Value is username for mentions, normalized tag for hashtags and link itself for urls
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextEntity {
+    pub kind: EntityKind,
+    pub start: usize,
+    pub end: usize,
+    pub value: String,
+}
+
+/// Characters allowed in usernames, see validate_username
+fn is_username_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '.' || c == '_'
+}
+
+/// Private function that gets byte length of the longest prefix of 'text' after 'skip' bytes
+/// where every char passes 'accept', 'skip' bytes are included
+fn scan(text: &str, skip: usize, accept: fn(char) -> bool) -> usize {
+    skip + text[skip..]
+        .find(|c: char| !accept(c))
+        .unwrap_or(text.len() - skip)
+}
+
+/// Private function that gets byte length of url at the start of 'text'
+/// Trailing punctuation and unbalanced closing bracket are left out, e.g. "(see https://a.b)."
+fn scan_url(text: &str) -> Option<usize> {
+    let scheme = ["https://", "http://"]
+        .into_iter()
+        .find(|s| text.starts_with(s))?;
+    let mut url = &text[..text.find(char::is_whitespace).unwrap_or(text.len())];
+    loop {
+        let trimmed = url.trim_end_matches(['.', ',', ';', ':', '!', '?', '\'', '"']);
+        let trimmed = match trimmed.strip_suffix(')') {
+            Some(rest) if trimmed.matches('(').count() < trimmed.matches(')').count() => rest,
+            _ => trimmed,
+        };
+        if trimmed == url {
+            break;
+        }
+        url = trimmed;
+    }
+    (url.len() > scheme.len()).then_some(url.len())
+}
+
+/// Extracts mentions, hashtags and urls from text in order of appearance
+/// Entities only start at the beginning of text or after whitespace or opening bracket,
+/// so url anchors, emails and "C#" aren't entities
+pub fn extract_entities(text: &str) -> Vec<TextEntity> {
+    let mut result: Vec<TextEntity> = Vec::new();
+    let mut prev: Option<char> = None;
+    let mut i = 0;
+
+    while let Some(c) = text[i..].chars().next() {
+        let rest = &text[i..];
+        let starts =
+            prev.is_none_or(|p| p.is_whitespace() || matches!(p, '(' | '[' | '{' | '"' | '\''));
+        let entity = match c {
+            _ if !starts => None,
+            '#' => {
+                let len = scan(rest, 1, is_tag_char);
+                normalize_tag(&rest[..len]).map(|tag| (len, EntityKind::Hashtag, tag))
+            }
+            '@' => {
+                // Trailing dots end sentences, usernames can't end with them anyway
+                let len = 1 + rest[1..scan(rest, 1, is_username_char)]
+                    .trim_end_matches('.')
+                    .len();
+                (len > 1).then(|| (len, EntityKind::Mention, rest[1..len].to_string()))
+            }
+            _ => scan_url(rest).map(|len| (len, EntityKind::Url, rest[..len].to_string())),
+        };
+
+        match entity {
+            Some((len, kind, value)) => {
+                result.push(TextEntity {
+                    kind,
+                    start: i,
+                    end: i + len,
+                    value,
+                });
+                prev = rest[..len].chars().next_back();
+                i += len;
+            }
+            None => {
+                prev = Some(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    result
+}
+
+/// Private function that gets unique values of entities of one kind in order of appearance
+fn entity_values(text: &str, kind: EntityKind) -> Vec<String> {
+    let mut result: Vec<String> = Vec::new();
+    for entity in extract_entities(text) {
+        if entity.kind == kind && !result.contains(&entity.value) {
+            result.push(entity.value);
+        }
+    }
+    result
+}
+
+/// Extracts normalized hashtags from text in order of appearance, duplicates are removed
+pub fn extract_hashtags(text: &str) -> Vec<String> {
+    entity_values(text, EntityKind::Hashtag)
+}
+
+/// Extracts mentioned usernames from text in order of appearance, at most MAX_MENTIONS
+pub fn extract_mentions(text: &str) -> Vec<String> {
+    let mut mentions = entity_values(text, EntityKind::Mention);
+    mentions.truncate(MAX_MENTIONS);
+    mentions
+}
+
+/// Tags of post from explicit tags and hashtags in content, at most MAX_TAGS
+pub fn merge_tags(explicit: Vec<String>, content: &str) -> Vec<String> {
+    let mut tags = explicit;
+    for tag in extract_hashtags(content) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags.truncate(MAX_TAGS);
+    tags
+}
+
+/// Splits search query into web search part and prefix terms
+/// Words ending with '*' outside of quotes are prefix terms, e.g. "rust prog*" -> ("rust", ["prog"])
+/// Prefix terms only keep word characters, so they are safe to use in tsquery
+pub fn split_search_query(query: &str) -> (String, Vec<String>) {
+    // Words with flag whether they are inside of an unclosed quote
+    let mut words: Vec<(&str, bool)> = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (i, c) in query.char_indices() {
+        if c == '"' {
+            in_quotes = !in_quotes;
+        } else if c.is_whitespace() && !in_quotes {
+            words.push((&query[start..i], false));
+            start = i + c.len_utf8();
+        }
+    }
+    words.push((&query[start..], in_quotes));
+
+    let mut text: Vec<&str> = Vec::new();
+    let mut prefixes: Vec<String> = Vec::new();
+    for (word, in_quotes) in words {
+        if in_quotes || word.starts_with(['"', '-']) || !word.ends_with('*') {
+            if !word.is_empty() {
+                text.push(word);
+            }
+            continue;
+        }
+        let prefix: String = word
+            .nfkc()
+            .filter(|c| is_tag_char(*c))
+            .collect::<String>()
+            .to_lowercase();
+        if !prefix.is_empty() {
+            prefixes.push(prefix);
+        }
+    }
+
+    (text.join(" "), prefixes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_unicode_tags() {
+        assert_eq!(normalize_tag("#Rust").as_deref(), Some("rust"));
+        assert_eq!(normalize_tag("Ｒｕｓｔ").as_deref(), Some("rust"));
+        assert_eq!(normalize_tag("Ünïcode").as_deref(), Some("ünïcode"));
+        assert_eq!(normalize_tag("हिन्दी").as_deref(), Some("हिन्दी"));
+        assert_eq!(normalize_tag("two words"), None);
+        assert_eq!(normalize_tag("2024"), None);
+        assert_eq!(normalize_tag("#"), None);
+    }
+
+    #[test]
+    fn extracts_hashtags() {
+        let text = "#Rust and #rust, (#async) https://a.b/c#anchor C#, #2024 #Тест!";
+        assert_eq!(extract_hashtags(text), vec!["rust", "async", "тест"]);
+    }
+
+    #[test]
+    fn extracts_entities_with_byte_offsets() {
+        let text = "Привет @bob. See (https://a.b/c_(d)), mail a@b.c #Тег @x.y_z";
+        let entities = extract_entities(text);
+        let entities: Vec<(EntityKind, &str, &str)> = entities
+            .iter()
+            .map(|e| (e.kind, &text[e.start..e.end], e.value.as_str()))
+            .collect();
+        assert_eq!(
+            entities,
+            vec![
+                (EntityKind::Mention, "@bob", "bob"),
+                (EntityKind::Url, "https://a.b/c_(d)", "https://a.b/c_(d)"),
+                (EntityKind::Hashtag, "#Тег", "тег"),
+                (EntityKind::Mention, "@x.y_z", "x.y_z"),
+            ]
+        );
+        assert_eq!(extract_mentions("@a @b @a @"), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn merges_explicit_tags_first() {
+        let explicit = vec!["go".to_string()];
+        assert_eq!(merge_tags(explicit, "#rust #go"), vec!["go", "rust"]);
+
+        let content: String = (0..20).map(|i| format!("#t{} ", i)).collect();
+        assert_eq!(merge_tags(Vec::new(), &content).len(), MAX_TAGS);
+    }
+
+    #[test]
+    fn splits_prefix_terms_from_search_query() {
+        let (text, prefixes) = split_search_query("\"rust prog*\" Lang* -skip* go");
+        assert_eq!(text, "\"rust prog*\" -skip* go");
+        assert_eq!(prefixes, vec!["lang"]);
+
+        let (text, prefixes) = split_search_query("'*  ");
+        assert_eq!(text, "");
+        assert!(prefixes.is_empty());
+    }
+}
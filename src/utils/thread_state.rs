@@ -1,25 +1,44 @@
 use crate::utils::snowflake::SnowflakeGenerator;
 use std::cell::RefCell;
+use std::sync::Mutex;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::{env, thread_local};
 
 static THREAD_COUNTER: AtomicUsize = AtomicUsize::new(0);
 
+/// States of finished threads, reused so worker ids don't run out
+/// Generator keeps its last timestamp and counter, so reused worker_id can't repeat ids
+static FREE_STATES: Mutex<Vec<ThreadState>> = Mutex::new(Vec::new());
+
 struct ThreadState {
     worker_id: u64,
     snowflake: SnowflakeGenerator,
 }
 
+/// Returns state of thread to FREE_STATES when thread exits
+struct Slot(RefCell<Option<ThreadState>>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        if let Some(state) = self.0.get_mut().take() {
+            FREE_STATES.lock().unwrap().push(state);
+        }
+    }
+}
+
 thread_local! {
-    static STATE: RefCell<Option<ThreadState>> = RefCell::new(None);
+    static STATE: Slot = const { Slot(RefCell::new(None)) };
 }
 
 fn thread_state<F, R>(f: F) -> R
 where
     F: FnOnce(&mut ThreadState) -> R,
 {
-    STATE.with(|cell| {
-        let mut opt = cell.borrow_mut();
+    STATE.with(|slot| {
+        let mut opt = slot.0.borrow_mut();
+        if opt.is_none() {
+            *opt = FREE_STATES.lock().unwrap().pop();
+        }
         if opt.is_none() {
             let id = THREAD_COUNTER.fetch_add(1, Ordering::Relaxed) as u64;
 